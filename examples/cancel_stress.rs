@@ -0,0 +1,53 @@
+//! Interleaves reads, timeouts and cancellations to check that no I/O operation is left behind
+//!
+//! Pass the vendor and product id of a device in hex (i.e. `cargo run --example cancel_stress -- 046D C52B`), otherwise the first device is used.
+//! Every read times out almost immediately, so most of them are abandoned while still pending and picked up again by the next read.
+//! They only get canceled when the device is reopened, which on Windows races the cancellation against incoming reports on real hardware.
+//! The cancel path itself is covered by the unit tests of the win32 backend. Nothing is written, so it is safe to run against any device.
+
+use std::time::Duration;
+
+use async_hid::{AccessMode, DeviceFilter, DeviceInfo, HidResult};
+use simple_logger::SimpleLogger;
+
+const ITERATIONS: usize = 1000;
+const REOPEN_INTERVAL: usize = 50;
+
+#[pollster::main]
+async fn main() -> HidResult<()> {
+    SimpleLogger::new().with_level(log::LevelFilter::Info).init().unwrap();
+
+    let args: Vec<u16> = std::env::args()
+        .skip(1)
+        .map(|arg| u16::from_str_radix(&arg, 16).expect("Ids must be hexadecimal"))
+        .collect();
+    let filter = match args.as_slice() {
+        [vendor_id, product_id] => DeviceFilter::new().vendor_id(*vendor_id).product_id(*product_id),
+        _ => DeviceFilter::new()
+    };
+    let info = DeviceInfo::find(|info| info.matches_filter(&filter))
+        .await?
+        .expect("Could not find device");
+    println!("Using {} (0x{:X} 0x{:X})", info.name, info.vendor_id, info.product_id);
+
+    let mut device = info.open(AccessMode::Read).await?;
+    let mut buffer = vec![0u8; 256];
+    let mut completed = 0;
+    for i in 0..ITERATIONS {
+        if i % REOPEN_INTERVAL == REOPEN_INTERVAL - 1 {
+            // Dropping the device cancels the read that is most likely still pending
+            drop(device);
+            device = info.open(AccessMode::Read).await?;
+        }
+        let timeout = Duration::from_micros((i % 4) as u64 * 500);
+        if device.read_input_report_timeout(&mut buffer, timeout).await?.is_some() {
+            completed += 1;
+        }
+        if i % 3 == 0 {
+            device.clear_input_reports()?;
+        }
+    }
+    device.close()?;
+    println!("Finished {ITERATIONS} iterations ({completed} reads completed in time)");
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use std::pin::pin;
+
+use async_hid::{AccessMode, DeviceFilter, DeviceInfo, HidResult};
+use futures_lite::StreamExt;
+use simple_logger::SimpleLogger;
+
+#[pollster::main]
+async fn main() -> HidResult<()> {
+    SimpleLogger::new().init().unwrap();
+
+    // The first connected gamepad
+    let filter = DeviceFilter::new().usage_page(0x1).usage_id(0x5);
+    let device = DeviceInfo::enumerate()
+        .await?
+        .find(|info: &DeviceInfo| info.matches_filter(&filter))
+        .await
+        .expect("Could not find device")
+        .open(AccessMode::Read)
+        .await?;
+
+    let mut events = pin!(device.input_events());
+    while let Some(event) = events.next().await {
+        for (usage, value) in event? {
+            println!("0x{:X}/0x{:X} = {}", usage.page, usage.id, value);
+        }
+    }
+    Ok(())
+}
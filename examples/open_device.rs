@@ -1,4 +1,4 @@
-use async_hid::{AccessMode, DeviceInfo, HidResult};
+use async_hid::{AccessMode, DeviceFilter, DeviceInfo, HidResult};
 use futures_lite::stream::StreamExt;
 use simple_logger::SimpleLogger;
 
@@ -6,11 +6,16 @@ use simple_logger::SimpleLogger;
 async fn main() -> HidResult<()> {
     SimpleLogger::new().init().unwrap();
 
+    let filter = DeviceFilter::new()
+        .usage_page(0x1)
+        .usage_id(0x1)
+        .vendor_id(0x46D)
+        .product_id(0xC016);
     let device = DeviceInfo::enumerate()
         .await?
-        .find(|info: &DeviceInfo| info.matches(0x1, 0x1, 0x46D, 0xC016))
+        .find(|info: &DeviceInfo| info.matches_filter(&filter))
         .await
-        //.find(|info| info.matches(0xFF00, 0x1, 0x1038, 0x2206))
+        //.find(|info| info.matches_filter(&DeviceFilter::new().usage_page(0xFF00).usage_id(0x1).vendor_id(0x1038).product_id(0x2206)))
         .expect("Could not find device")
         .open(AccessMode::Read)
         .await?;
@@ -1,16 +1,20 @@
 use std::time::Duration;
-use async_io::Timer;
-use async_hid::{AccessMode, DeviceInfo, HidResult};
-use futures_lite::{FutureExt, StreamExt};
+use async_hid::{AccessMode, DeviceFilter, DeviceInfo, HidResult};
+use futures_lite::StreamExt;
 use simple_logger::SimpleLogger;
 
 #[pollster::main]
 async fn main() -> HidResult<()> {
     SimpleLogger::new().init().unwrap();
 
+    let filter = DeviceFilter::new()
+        .usage_page(0xFF00)
+        .usage_id(0x1)
+        .vendor_id(0x1038)
+        .product_id(0x2206);
     let device = DeviceInfo::enumerate()
         .await?
-        .find(|info: &DeviceInfo| info.matches(0xFF00, 0x1, 0x1038, 0x2206))
+        .find(|info: &DeviceInfo| info.matches_filter(&filter))
         .await
         .inspect(|info| {
             println!(
@@ -29,7 +33,7 @@ async fn main() -> HidResult<()> {
 
     let mut buffer = [0u8; 8];
     loop {
-        let size = device.read_input_report(&mut buffer).or(async { Timer::after(Duration::from_secs(4)).await; Ok(0) }).await?;
+        let size = device.read_input_report_timeout(&mut buffer, Duration::from_secs(4)).await?.unwrap_or(0);
         //sleep(std::time::Duration::from_millis(10));
         println!("{:?}", &buffer[..size]);
     }
@@ -1,4 +1,4 @@
-use async_hid::{AccessMode, DeviceInfo, HidError, HidResult};
+use async_hid::{AccessMode, DeviceFilter, DeviceInfo, HidError, HidResult};
 use futures_lite::StreamExt;
 use simple_logger::SimpleLogger;
 use tokio::spawn;
@@ -8,9 +8,14 @@ async fn main() -> HidResult<()> {
     SimpleLogger::new().init().unwrap();
 
     spawn(async {
+        let filter = DeviceFilter::new()
+            .usage_page(0xFFC0)
+            .usage_id(0x1)
+            .vendor_id(0x1038)
+            .product_id(0x2206);
         let device = DeviceInfo::enumerate()
             .await?
-            .find(|info: &DeviceInfo| info.matches(0xFFC0, 0x1, 0x1038, 0x2206))
+            .find(|info: &DeviceInfo| info.matches_filter(&filter))
             .await
             .inspect(|info| {
                 println!(
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_hid::{AccessMode, DeviceFilter, DeviceInfo, HidError, HidResult};
+use futures_lite::StreamExt;
+use simple_logger::SimpleLogger;
+use tokio::spawn;
+use tokio::time::sleep;
+
+#[tokio::main]
+async fn main() -> HidResult<()> {
+    SimpleLogger::new().init().unwrap();
+
+    let filter = DeviceFilter::new()
+        .usage_page(0xFFC0)
+        .usage_id(0x1)
+        .vendor_id(0x1038)
+        .product_id(0x2206);
+    let device = DeviceInfo::enumerate()
+        .await?
+        .find(|info: &DeviceInfo| info.matches_filter(&filter))
+        .await
+        .expect("Could not find device")
+        .open(AccessMode::ReadWrite)
+        .await?;
+    let device = Arc::new(device);
+
+    let reader = spawn({
+        let device = device.clone();
+        async move {
+            let mut buffer = [0u8; 8];
+            for _ in 0..4 {
+                let size = device.read_input_report(&mut buffer).await?;
+                println!("{:?}", &buffer[..size]);
+            }
+            Ok::<(), HidError>(())
+        }
+    });
+
+    let writer = spawn({
+        let device = device.clone();
+        async move {
+            for _ in 0..4 {
+                device.write_output_report(&[0x0, 0xb0]).await?;
+                sleep(Duration::from_millis(500)).await;
+            }
+            Ok::<(), HidError>(())
+        }
+    });
+
+    // The device stays open until both tasks have dropped their handle
+    drop(device);
+    writer.await.unwrap()?;
+    reader.await.unwrap()?;
+    Ok(())
+}
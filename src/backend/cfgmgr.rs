@@ -0,0 +1,181 @@
+//! Helpers for the configuration manager that are shared between the windows backends
+
+use std::mem::size_of;
+
+use windows::core::{GUID, HRESULT, PCWSTR};
+use windows::Win32::Devices::DeviceAndDriverInstallation::{
+    CM_Get_DevNode_PropertyW, CM_Get_Device_Interface_PropertyW, CM_Get_Parent, CM_Locate_DevNodeW, CM_MapCrToWin32Err, CM_LOCATE_DEVNODE_NORMAL, CONFIGRET, CR_SUCCESS
+};
+use windows::Win32::Devices::Properties::{
+    DEVPKEY_Device_CompatibleIds, DEVPKEY_Device_ContainerId, DEVPKEY_Device_FriendlyName, DEVPKEY_Device_InstanceId, DEVPKEY_Device_LocationPaths, DEVPROPKEY, DEVPROPTYPE,
+    DEVPROP_TYPE_GUID, DEVPROP_TYPE_STRING, DEVPROP_TYPE_STRING_LIST
+};
+use windows::Win32::Foundation::E_FAIL;
+
+use crate::{ensure, BusType, ErrorSource, HidError, HidResult};
+
+/// Returns the container id of the device that exposes the given device interface
+///
+/// All functions of a physical device (for example the interfaces of a composite USB device) share the same container id.
+pub fn get_container_id(interface: PCWSTR) -> HidResult<GUID> {
+    let devinst = locate_device_node(interface)?;
+    let mut property_type = DEVPROPTYPE::default();
+    let mut container_id = GUID::zeroed();
+    let mut len = size_of::<GUID>() as u32;
+    check(unsafe {
+        CM_Get_DevNode_PropertyW(
+            devinst,
+            &DEVPKEY_Device_ContainerId,
+            &mut property_type,
+            Some(&mut container_id as *mut GUID as _),
+            &mut len,
+            0
+        )
+    })?;
+    ensure!(property_type == DEVPROP_TYPE_GUID, HidError::custom("The container id of the device is not a guid"));
+    Ok(container_id)
+}
+
+/// Returns the friendly name of the device that exposes the given device interface
+///
+/// This is the name shown by the device manager, which is usually set by the driver or the user.
+#[cfg_attr(feature = "winrt", allow(dead_code))]
+pub fn get_friendly_name(interface: PCWSTR) -> HidResult<String> {
+    let devinst = locate_device_node(interface)?;
+    let mut property_type = DEVPROPTYPE::default();
+    let mut buffer = [0u16; 256];
+    let mut len = (buffer.len() * size_of::<u16>()) as u32;
+    check(unsafe {
+        CM_Get_DevNode_PropertyW(
+            devinst,
+            &DEVPKEY_Device_FriendlyName,
+            &mut property_type,
+            Some(buffer.as_mut_ptr() as _),
+            &mut len,
+            0
+        )
+    })?;
+    ensure!(property_type == DEVPROP_TYPE_STRING, HidError::custom("The friendly name of the device is not a string"));
+    let name = buffer
+        .split(|c| *c == 0x0)
+        .map(String::from_utf16_lossy)
+        .next()
+        .unwrap_or_default();
+    Ok(name)
+}
+
+/// Determines the bus of the device that exposes the given device interface
+///
+/// The device node of the interface belongs to the HID class driver, so the bus is derived from the compatible ids of its parent.
+pub fn get_bus_type(interface: PCWSTR) -> HidResult<BusType> {
+    let devinst = locate_device_node(interface)?;
+    let mut parent = 0;
+    check(unsafe { CM_Get_Parent(&mut parent, devinst, 0) })?;
+    let mut property_type = DEVPROPTYPE::default();
+    let mut buffer = [0u16; 1024];
+    let mut len = (buffer.len() * size_of::<u16>()) as u32;
+    check(unsafe {
+        CM_Get_DevNode_PropertyW(
+            parent,
+            &DEVPKEY_Device_CompatibleIds,
+            &mut property_type,
+            Some(buffer.as_mut_ptr() as _),
+            &mut len,
+            0
+        )
+    })?;
+    ensure!(property_type == DEVPROP_TYPE_STRING_LIST, HidError::custom("The compatible ids of the device are not a string list"));
+    let bus_type = buffer[..len as usize / size_of::<u16>()]
+        .split(|c| *c == 0x0)
+        .map(|id| String::from_utf16_lossy(id).to_ascii_uppercase())
+        .find_map(|id| match id {
+            _ if id.contains("USB") => Some(BusType::Usb),
+            _ if id.contains("BTHENUM") || id.contains("BTHLEDEVICE") => Some(BusType::Bluetooth),
+            _ if id.contains("PNP0C50") => Some(BusType::I2c),
+            _ if id.contains("PNP0C51") => Some(BusType::Spi),
+            _ => None
+        })
+        .unwrap_or_default();
+    Ok(bus_type)
+}
+
+/// Returns the location path (i.e. `PCIROOT(0)#PCI(1400)#USBROOT(0)#USB(2)`) of the device that exposes the given device interface
+///
+/// The HID device node itself usually has no location, so the location of its closest ancestor is used.
+/// Devices without any location (i.e. Bluetooth devices) fall back to the instance id of their device node.
+pub fn get_location(interface: PCWSTR) -> HidResult<String> {
+    let devinst = locate_device_node(interface)?;
+    let mut current = devinst;
+    loop {
+        if let Some(path) = get_devnode_strings(current, &DEVPKEY_Device_LocationPaths).ok().and_then(|paths| paths.into_iter().next()) {
+            return Ok(path);
+        }
+        let mut parent = 0;
+        if unsafe { CM_Get_Parent(&mut parent, current, 0) } != CR_SUCCESS {
+            break;
+        }
+        current = parent;
+    }
+    get_devnode_strings(devinst, &DEVPKEY_Device_InstanceId)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| HidError::custom("The instance id of the device is empty"))
+}
+
+/// Reads a string or string list property of a device node
+fn get_devnode_strings(devinst: u32, key: &DEVPROPKEY) -> HidResult<Vec<String>> {
+    let mut property_type = DEVPROPTYPE::default();
+    let mut buffer = [0u16; 1024];
+    let mut len = (buffer.len() * size_of::<u16>()) as u32;
+    check(unsafe { CM_Get_DevNode_PropertyW(devinst, key, &mut property_type, Some(buffer.as_mut_ptr() as _), &mut len, 0) })?;
+    ensure!(
+        property_type == DEVPROP_TYPE_STRING || property_type == DEVPROP_TYPE_STRING_LIST,
+        HidError::custom("The property of the device is not a string")
+    );
+    let strings = buffer[..len as usize / size_of::<u16>()]
+        .split(|c| *c == 0x0)
+        .filter(|s| !s.is_empty())
+        .map(String::from_utf16_lossy)
+        .collect();
+    Ok(strings)
+}
+
+/// Finds the device node that exposes the given device interface
+fn locate_device_node(interface: PCWSTR) -> HidResult<u32> {
+    let mut property_type = DEVPROPTYPE::default();
+    let mut instance_id = [0u16; 256];
+    let mut len = (instance_id.len() * size_of::<u16>()) as u32;
+    check(unsafe {
+        CM_Get_Device_Interface_PropertyW(
+            interface,
+            &DEVPKEY_Device_InstanceId,
+            &mut property_type,
+            Some(instance_id.as_mut_ptr() as _),
+            &mut len,
+            0
+        )
+    })?;
+    ensure!(property_type == DEVPROP_TYPE_STRING, HidError::custom("The instance id of the device is not a string"));
+
+    let mut devinst = 0;
+    check(unsafe { CM_Locate_DevNodeW(&mut devinst, PCWSTR::from_raw(instance_id.as_ptr()), CM_LOCATE_DEVNODE_NORMAL) })?;
+    Ok(devinst)
+}
+
+fn check(result: CONFIGRET) -> HidResult<()> {
+    match result {
+        CR_SUCCESS => Ok(()),
+        err => Err(err.into())
+    }
+}
+
+impl From<CONFIGRET> for ErrorSource {
+    fn from(value: CONFIGRET) -> Self {
+        const UNKNOWN_ERROR: u32 = 0xFFFF;
+        let hresult = match unsafe { CM_MapCrToWin32Err(value, UNKNOWN_ERROR) } {
+            UNKNOWN_ERROR => E_FAIL,
+            win32 => HRESULT::from_win32(win32),
+        };
+        ErrorSource::PlatformSpecific(windows::core::Error::from(hresult))
+    }
+}
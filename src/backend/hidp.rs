@@ -0,0 +1,90 @@
+//! Helpers for the HID parser of the Windows HID class driver that are shared between the windows backends
+
+use windows::Win32::Devices::HumanInterfaceDevice::{
+    HidP_Feature, HidP_GetButtonCaps, HidP_GetCaps, HidP_GetData, HidP_GetValueCaps, HidP_Input, HidP_MaxDataListLength, HidP_Output, HIDP_BUTTON_CAPS, HIDP_CAPS, HIDP_DATA, HIDP_VALUE_CAPS, PHIDP_PREPARSED_DATA
+};
+
+use crate::report::sign_extend;
+use crate::{ensure, HidError, HidResult, ReportType, Usage};
+
+/// Extracts the value of every control of the given report using the preparsed data of the device
+///
+/// `report` is expected to be in the format returned by the backends, which means that the report id is missing
+/// unless `numbered` is set.
+/// Unlike the descriptor based decoding on the other platforms, buttons are only reported while they are pressed.
+pub fn decode_report(preparsed_data: PHIDP_PREPARSED_DATA, report_type: ReportType, numbered: bool, report: &[u8]) -> HidResult<Vec<(Usage, i64)>> {
+    let mut caps = HIDP_CAPS::default();
+    unsafe { HidP_GetCaps(preparsed_data, &mut caps) }.ok()?;
+    let (hid_report_type, report_len, button_caps_len, value_caps_len) = match report_type {
+        ReportType::Input => (HidP_Input, caps.InputReportByteLength, caps.NumberInputButtonCaps, caps.NumberInputValueCaps),
+        ReportType::Output => (HidP_Output, caps.OutputReportByteLength, caps.NumberOutputButtonCaps, caps.NumberOutputValueCaps),
+        ReportType::Feature => (HidP_Feature, caps.FeatureReportByteLength, caps.NumberFeatureButtonCaps, caps.NumberFeatureValueCaps)
+    };
+
+    let mut button_caps = vec![HIDP_BUTTON_CAPS::default(); button_caps_len as usize];
+    let mut value_caps = vec![HIDP_VALUE_CAPS::default(); value_caps_len as usize];
+    let mut button_caps_len = button_caps_len;
+    let mut value_caps_len = value_caps_len;
+    unsafe {
+        if button_caps_len > 0 {
+            HidP_GetButtonCaps(hid_report_type, button_caps.as_mut_ptr(), &mut button_caps_len, preparsed_data).ok()?;
+        }
+        if value_caps_len > 0 {
+            HidP_GetValueCaps(hid_report_type, value_caps.as_mut_ptr(), &mut value_caps_len, preparsed_data).ok()?;
+        }
+    }
+    button_caps.truncate(button_caps_len as usize);
+    value_caps.truncate(value_caps_len as usize);
+
+    // The parser always expects a full length report starting with the report id
+    let offset = if numbered { 0 } else { 1 };
+    ensure!(report_len as usize > offset, HidError::custom("Device has no reports of this type"));
+    let mut buffer = vec![0u8; report_len as usize];
+    let len = report.len().min(buffer.len().saturating_sub(offset));
+    buffer[offset..offset + len].copy_from_slice(&report[..len]);
+
+    let mut data = vec![HIDP_DATA::default(); unsafe { HidP_MaxDataListLength(hid_report_type, preparsed_data) } as usize];
+    let mut data_len = data.len() as u32;
+    unsafe { HidP_GetData(hid_report_type, data.as_mut_ptr(), &mut data_len, preparsed_data, &mut buffer) }.ok()?;
+
+    let values = data[..data_len as usize]
+        .iter()
+        .filter_map(|data| {
+            if let Some(usage) = button_caps.iter().find_map(|c| button_usage(c, data.DataIndex)) {
+                return Some((usage, 1));
+            }
+            value_caps.iter().find_map(|c| {
+                let usage = value_usage(c, data.DataIndex)?;
+                let raw = unsafe { data.Anonymous.RawValue } as u64;
+                let value = match c.LogicalMin < 0 {
+                    true => sign_extend(raw, c.BitSize as usize),
+                    false => raw as i64
+                };
+                Some((usage, value))
+            })
+        })
+        .collect();
+    Ok(values)
+}
+
+fn button_usage(caps: &HIDP_BUTTON_CAPS, data_index: u16) -> Option<Usage> {
+    unsafe {
+        match caps.IsRange.as_bool() {
+            true => (caps.Anonymous.Range.DataIndexMin..=caps.Anonymous.Range.DataIndexMax)
+                .contains(&data_index)
+                .then(|| Usage::new(caps.UsagePage, caps.Anonymous.Range.UsageMin + (data_index - caps.Anonymous.Range.DataIndexMin))),
+            false => (caps.Anonymous.NotRange.DataIndex == data_index).then(|| Usage::new(caps.UsagePage, caps.Anonymous.NotRange.Usage))
+        }
+    }
+}
+
+fn value_usage(caps: &HIDP_VALUE_CAPS, data_index: u16) -> Option<Usage> {
+    unsafe {
+        match caps.IsRange.as_bool() {
+            true => (caps.Anonymous.Range.DataIndexMin..=caps.Anonymous.Range.DataIndexMax)
+                .contains(&data_index)
+                .then(|| Usage::new(caps.UsagePage, caps.Anonymous.Range.UsageMin + (data_index - caps.Anonymous.Range.DataIndexMin))),
+            false => (caps.Anonymous.NotRange.DataIndex == data_index).then(|| Usage::new(caps.UsagePage, caps.Anonymous.NotRange.Usage))
+        }
+    }
+}
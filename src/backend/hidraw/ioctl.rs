@@ -1,16 +1,29 @@
 //! The IOCTL calls we need for the native linux backend
 
-use nix::ioctl_read;
+use std::ffi::c_void;
+
+use nix::{ioctl_read, ioctl_readwrite, ioctl_readwrite_buf};
+
+// From linux/hid.h
+pub const HID_MAX_DESCRIPTOR_SIZE: usize = 4096;
 
 // From linux/hidraw.h
 const HIDRAW_IOC_MAGIC: u8 = b'H';
 const HIDRAW_IOC_GRDESCSIZE: u8 = 0x01;
-//const HIDRAW_SET_FEATURE: u8 = 0x06;
-//const HIDRAW_GET_FEATURE: u8 = 0x07;
+const HIDRAW_IOC_GRDESC: u8 = 0x02;
+const HIDRAW_SET_FEATURE: u8 = 0x06;
+const HIDRAW_GET_FEATURE: u8 = 0x07;
+const HIDRAW_GET_INPUT: u8 = 0x0A;
+
+#[repr(C)]
+pub struct RawReportDescriptor {
+    pub size: u32,
+    pub value: [u8; HID_MAX_DESCRIPTOR_SIZE]
+}
 
 ioctl_read!(hidraw_ioc_grdescsize, HIDRAW_IOC_MAGIC, HIDRAW_IOC_GRDESCSIZE, i32);
+ioctl_read!(hidraw_ioc_grdesc, HIDRAW_IOC_MAGIC, HIDRAW_IOC_GRDESC, RawReportDescriptor);
 
-/*
 ioctl_readwrite_buf!(
     hidraw_ioc_set_feature,
     HIDRAW_IOC_MAGIC,
@@ -23,4 +36,26 @@ ioctl_readwrite_buf!(
     HIDRAW_GET_FEATURE,
     u8
 );
-*/
+ioctl_readwrite_buf!(
+    hidraw_ioc_get_input,
+    HIDRAW_IOC_MAGIC,
+    HIDRAW_GET_INPUT,
+    u8
+);
+
+// From linux/usbdevice_fs.h
+const USBDEVFS_IOC_MAGIC: u8 = b'U';
+const USBDEVFS_CONTROL: u8 = 0x00;
+
+#[repr(C)]
+pub struct UsbCtrlTransfer {
+    pub request_type: u8,
+    pub request: u8,
+    pub value: u16,
+    pub index: u16,
+    pub length: u16,
+    pub timeout: u32,
+    pub data: *mut c_void
+}
+
+ioctl_readwrite!(usbdevfs_control, USBDEVFS_IOC_MAGIC, USBDEVFS_CONTROL, UsbCtrlTransfer);
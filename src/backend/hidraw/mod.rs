@@ -1,69 +1,127 @@
-mod descriptor;
 mod ioctl;
 mod utils;
 
-use std::fs::{OpenOptions, read_dir, read_to_string};
-use std::os::fd::{AsRawFd, OwnedFd};
+use std::fs::{OpenOptions, read, read_dir, read_to_string};
+use std::os::fd::{AsRawFd, IntoRawFd, OwnedFd};
 use std::os::unix::fs::OpenOptionsExt;
+use std::num::NonZeroU8;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
 
 use futures_core::Stream;
+use futures_lite::FutureExt;
+use futures_timer::Delay;
+use nix::errno::Errno;
 use nix::fcntl::OFlag;
-use nix::unistd::{read, write};
+use nix::unistd;
 
-use crate::backend::hidraw::descriptor::HidrawReportDescriptor;
 use crate::backend::hidraw::utils::{iter, TryIterExt};
-use crate::{ensure, DeviceInfo, ErrorSource, HidError, HidResult, SerialNumberExt, AccessMode};
+use crate::descriptor::ReportDescriptor;
+use crate::filter::any_matches_ids;
+use crate::{ensure, AccessState, BackendType, EnumerationError, BusType, DeviceFilter, DeviceInfo, EnumerateOptions, ErrorSource, HidError, HidResult, SerialNumberExt, AccessMode, ReadConfig, ReadOutcome, ReportType, Usage};
 
-use crate::backend::hidraw::async_api::{AsyncFd, read_with, write_with};
-use crate::backend::hidraw::ioctl::hidraw_ioc_grdescsize;
+use crate::backend::hidraw::async_api::{AsyncFd, into_inner, read_with, write_with};
+use crate::backend::hidraw::ioctl::{
+    hidraw_ioc_get_feature, hidraw_ioc_get_input, hidraw_ioc_grdesc, hidraw_ioc_grdescsize, hidraw_ioc_set_feature, usbdevfs_control, RawReportDescriptor, UsbCtrlTransfer,
+    HID_MAX_DESCRIPTOR_SIZE
+};
 
-pub async fn enumerate() -> HidResult<impl Stream<Item = DeviceInfo> + Send + Unpin> {
+// From linux/hid.h
+const HID_MAX_BUFFER_SIZE: usize = 16384;
+
+const CONTROL_TRANSFER_TIMEOUT_MS: u32 = 1000;
+
+// From linux/usb/ch9.h
+const USB_DIR_IN: u8 = 0x80;
+const USB_REQ_GET_DESCRIPTOR: u8 = 0x06;
+const USB_DT_STRING: u16 = 0x03;
+
+pub const BACKEND_TYPE: BackendType = BackendType::Hidraw;
+
+pub async fn enumerate(
+    _options: EnumerateOptions, filters: &[DeviceFilter]
+) -> HidResult<impl Stream<Item = Result<DeviceInfo, EnumerationError>> + Send + Unpin> {
     let devices = read_dir("/sys/class/hidraw/")?
         .map(|r| r.map(|e| e.path()))
         .try_collect_vec()?;
+    let filters = filters.to_vec();
     let devices = devices
         .into_iter()
-        .map(get_device_info_raw)
-        .filter_map(|r| {
-            r.map_err(|e| log::trace!("Failed to query device information\n\tbecause {e:?}"))
-                .ok()
-        })
-        .flatten();
+        .flat_map(move |path| {
+            let device = path.to_string_lossy().into_owned();
+            match get_device_info_raw(path, &filters) {
+                Ok(infos) => infos.into_iter().map(Ok).collect(),
+                Err(error) => vec![Err(EnumerationError { device, error })]
+            }
+        });
     Ok(iter(devices))
 }
 
-fn get_device_info_raw(path: PathBuf) -> HidResult<Vec<DeviceInfo>> {
+pub fn clear_enumeration_cache() {
+    // Enumeration doesn't open any devices, so there is nothing to cache
+}
+
+pub async fn get_device_info(id: &BackendDeviceId) -> HidResult<DeviceInfo> {
+    let path = Path::new("/sys/class/hidraw/").join(id.file_name().ok_or(HidError::custom("Invalid device id"))?);
+    // The first top-level collection of the report descriptor is the one enumeration lists first
+    get_device_info_raw(path, &[])?
+        .into_iter()
+        .next()
+        .ok_or(HidError::custom("Device has no top-level collections"))
+}
+
+fn get_device_info_raw(path: PathBuf, filters: &[DeviceFilter]) -> HidResult<Vec<DeviceInfo>> {
     let properties = read_to_string(path.join("uevent"))?;
+    // The node is always named after the sysfs entry, so the entry can stand in for a missing DEVNAME
     let id = read_property(&properties, "DEVNAME")
+        .or_else(|| path.file_name().and_then(|name| name.to_str()))
         .ok_or(HidError::custom("Can't find dev name"))
         .and_then(mange_dev_name)?;
 
+    // The `device` link points straight at the hid device, no matter how deep it sits in the hierarchy (i.e. for uhid devices)
     let properties = read_to_string(path.join("device/uevent"))?;
 
-    let (_bus, vendor_id, product_id) = read_property(&properties, "HID_ID")
+    let (bus, vendor_id, product_id) = read_property(&properties, "HID_ID")
         .and_then(parse_hid_vid_pid)
         .ok_or(HidError::custom("Can't find hid ids"))?;
+    if !any_matches_ids(filters, vendor_id, product_id) {
+        return Ok(Vec::new());
+    }
 
     let name = read_property(&properties, "HID_NAME")
-        .ok_or(HidError::custom("Can't find hid name"))?
+        .unwrap_or_default()
         .to_string();
 
+    // Bluetooth devices report their MAC address here, which is just as unique as a serial number
     let serial_number = read_property(&properties, "HID_UNIQ")
         .filter(|s| !s.is_empty())
         .map(str::to_string);
 
+    let polling_interval = read_polling_interval(&path);
+    let physical_device_id = read_physical_device_id(&path);
+    let release_number = read_release_number(&path).unwrap_or(0);
+
     let info = DeviceInfo {
         id: id.into(),
         name,
+        // HID_NAME usually starts with the manufacturer, but there is no reliable way to split it off
+        manufacturer: None,
         product_id,
         vendor_id,
         usage_id: 0,
         usage_page: 0,
-        private_data: BackendPrivateData { serial_number }
+        polling_interval,
+        bus_type: parse_bus_type(bus),
+        release_number,
+        private_data: BackendPrivateData {
+            serial_number,
+            physical_device_id
+        }
     };
 
-    let results = HidrawReportDescriptor::from_syspath(&path)
+    let results = read_report_descriptor(&path)
         .map(|descriptor| {
             descriptor
                 .usages()
@@ -78,6 +136,99 @@ fn get_device_info_raw(path: PathBuf) -> HidResult<Vec<DeviceInfo>> {
     Ok(results)
 }
 
+/// Read the report descriptor given the "base" sysfs of the device
+fn read_report_descriptor(syspath: &Path) -> HidResult<ReportDescriptor> {
+    let descriptor = read(syspath.join("device/report_descriptor"))?;
+    Ok(ReportDescriptor::from_vec(descriptor))
+}
+
+/// Read the interval of the interrupt in endpoint given the "base" sysfs of the device
+///
+/// Only USB devices have endpoints, so this returns `None` for every other bus
+fn read_polling_interval(syspath: &Path) -> Option<Duration> {
+    // The parent of the hid device is the usb interface
+    let interface = syspath.join("device").canonicalize().ok()?;
+    let interface = interface.parent()?;
+    read_dir(interface)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().starts_with("ep_"))
+        .map(|e| e.path())
+        .filter(|ep| read_to_string(ep.join("direction")).is_ok_and(|s| s.trim() == "in"))
+        .filter(|ep| read_to_string(ep.join("type")).is_ok_and(|s| s.trim() == "Interrupt"))
+        .find_map(|ep| read_to_string(ep.join("interval")).ok().and_then(|s| parse_interval(s.trim())))
+}
+
+/// Find the sysfs path of the physical device given the "base" sysfs of the device
+///
+/// For USB devices this is the usb device that owns the interface, for every other bus it is the hid device itself
+fn read_physical_device_id(syspath: &Path) -> Option<String> {
+    let device = syspath.join("device").canonicalize().ok()?;
+    let physical = usb_device_syspath(&device).unwrap_or(device);
+    Some(physical.to_string_lossy().into_owned())
+}
+
+/// Read the `bcdDevice` field of the usb device given the "base" sysfs of the device
+fn read_release_number(syspath: &Path) -> Option<u16> {
+    let device = syspath.join("device").canonicalize().ok()?;
+    let release = read_to_string(usb_device_syspath(&device)?.join("bcdDevice")).ok()?;
+    u16::from_str_radix(release.trim(), 16).ok()
+}
+
+/// Find the sysfs path of the usb device that owns the given hid device
+///
+/// The parent of a usb hid device is the usb interface, which in turn is a child of the usb device
+fn usb_device_syspath(hid_device: &Path) -> Option<PathBuf> {
+    let interface = hid_device.parent()?;
+    match interface.join("bInterfaceNumber").exists() {
+        true => interface.parent().map(Path::to_path_buf),
+        false => None
+    }
+}
+
+/// Find the sysfs path of the hid device behind the given hidraw node
+///
+/// The name of the hid device ends with a sequence number (i.e. `0003:046D:C52B.0007`) that the kernel never hands out twice,
+/// unlike the number of the hidraw node, so the path identifies a single connection of the device.
+fn hid_device_syspath(id: &Path) -> Option<PathBuf> {
+    Path::new("/sys/class/hidraw/")
+        .join(id.file_name()?)
+        .join("device")
+        .canonicalize()
+        .ok()
+}
+
+/// Find the usbfs node (i.e. `/dev/bus/usb/001/002`) of the usb device that owns the given hidraw node
+fn usb_device_node(id: &Path) -> Option<PathBuf> {
+    let hid_device = hid_device_syspath(id)?;
+    let properties = read_to_string(usb_device_syspath(&hid_device)?.join("uevent")).ok()?;
+    read_property(&properties, "DEVNAME").and_then(|name| mange_dev_name(name).ok())
+}
+
+/// Runs a blocking operation on a separate thread, so it doesn't stall the executor
+async fn unblock<T: Send + 'static>(operation: impl FnOnce() -> T + Send + 'static) -> HidResult<T> {
+    let (sender, receiver) = async_channel::bounded(1);
+    thread::spawn(move || {
+        // The channel has room for exactly this value, so this only fails if the caller stopped waiting
+        let _ = sender.try_send(operation());
+    });
+    receiver
+        .recv()
+        .await
+        .map_err(|_| HidError::custom("Blocking operation panicked"))
+}
+
+/// Parses the interval format used by the usb sysfs (i.e. `8ms` or `125us`)
+fn parse_interval(s: &str) -> Option<Duration> {
+    if let Some(ms) = s.strip_suffix("ms") {
+        ms.parse().ok().map(Duration::from_millis)
+    } else if let Some(us) = s.strip_suffix("us") {
+        us.parse().ok().map(Duration::from_micros)
+    } else {
+        None
+    }
+}
+
 fn read_property<'a>(properties: &'a str, key: &str) -> Option<&'a str> {
     properties
         .lines()
@@ -85,6 +236,48 @@ fn read_property<'a>(properties: &'a str, key: &str) -> Option<&'a str> {
         .find_map(|(k, v)| (k == key).then_some(v))
 }
 
+pub fn is_connected(id: &BackendDeviceId) -> bool {
+    // The device node is removed as soon as the device gets unplugged, but the same node might belong to a different device later on
+    id.exists()
+}
+
+pub fn access_state(id: &BackendDeviceId) -> HidResult<AccessState> {
+    let result = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags((OFlag::O_CLOEXEC | OFlag::O_NONBLOCK).bits())
+        .open(id);
+    match result {
+        Ok(_) => Ok(AccessState::Available),
+        Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => Ok(AccessState::PermissionDenied),
+        Err(err) if err.raw_os_error() == Some(Errno::EBUSY as i32) => Ok(AccessState::BusyExclusive),
+        Err(err) => Err(err.into())
+    }
+}
+
+pub fn location(id: &BackendDeviceId) -> Option<String> {
+    // The sysfs path of the hid device contains the whole chain of buses and ports it is connected through
+    let device = hid_device_syspath(id)?;
+    Some(device.to_string_lossy().into_owned())
+}
+
+pub fn parse_device_id(id: &str) -> HidResult<BackendDeviceId> {
+    let dev_name = id.trim_end_matches('/');
+    let dev_name = dev_name.strip_prefix("/sys/class/hidraw/").unwrap_or(dev_name);
+    let path = mange_dev_name(dev_name)?;
+    ensure!(
+        path.file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with("hidraw")),
+        HidError::custom(format!("{id} is not a hidraw device"))
+    );
+    Ok(path)
+}
+
+pub fn format_device_id(id: &BackendDeviceId) -> String {
+    id.to_string_lossy().into_owned()
+}
+
 fn mange_dev_name(dev_name: &str) -> HidResult<PathBuf> {
     let path = Path::new(dev_name);
     if path.is_absolute() {
@@ -100,85 +293,391 @@ fn mange_dev_name(dev_name: &str) -> HidResult<PathBuf> {
     }
 }
 
+/// Parses the `HID_ID` property (i.e. `0003:0000046D:0000C52B`)
+///
+/// Every field has to be valid, so a malformed field can't shift the following ones into the wrong place.
 fn parse_hid_vid_pid(s: &str) -> Option<(u16, u16, u16)> {
-    let mut elems = s.split(':').filter_map(|s| u16::from_str_radix(s, 16).ok());
-    let devtype = elems.next()?;
-    let vendor = elems.next()?;
-    let product = elems.next()?;
+    let mut elems = s
+        .split(':')
+        .map(|s| u32::from_str_radix(s, 16).ok().and_then(|v| u16::try_from(v).ok()));
+    let devtype = elems.next()??;
+    let vendor = elems.next()??;
+    let product = elems.next()??;
 
     Some((devtype, vendor, product))
 }
 
+/// Maps the `BUS_*` constants from `linux/input.h`
+fn parse_bus_type(bus: u16) -> BusType {
+    match bus {
+        0x03 => BusType::Usb,
+        0x05 => BusType::Bluetooth,
+        0x18 => BusType::I2c,
+        0x1C => BusType::Spi,
+        _ => BusType::Unknown
+    }
+}
+
 impl SerialNumberExt for DeviceInfo {
     fn serial_number(&self) -> Option<&str> {
-        self.private_data
-            .serial_number
-            .as_ref()
-            .map(String::as_str)
+        self.private_data.serial_number.as_deref()
+    }
+}
+
+impl DeviceInfo {
+    pub(crate) fn physical_device_id(&self) -> Option<&str> {
+        self.private_data.physical_device_id.as_deref()
     }
 }
 
 
 #[derive(Debug)]
 pub struct BackendDevice {
-    fd: AsyncFd
+    id: BackendDeviceId,
+    /// The hid device behind the node at the time it was opened
+    hid_device: Option<PathBuf>,
+    fd: AsyncFd,
+    descriptor: ReportDescriptor,
+    numbered_input_reports: bool,
+    latest_only: bool,
+    dropped: AtomicU64,
+    usb_device: Option<PathBuf>
 }
 
 impl BackendDevice {
-    pub async fn read_input_report(&self, buf: &mut [u8]) -> HidResult<usize> {
-        read_with(&self.fd, |fd| read(fd.as_raw_fd(), buf).map_err(BackendError::from))
+    pub fn is_connected(&self) -> bool {
+        match &self.hid_device {
+            // The node might belong to a different device by now
+            Some(hid_device) => hid_device_syspath(&self.id).as_ref() == Some(hid_device),
+            None => is_connected(&self.id)
+        }
+    }
+
+    pub async fn read_input_report(&self, buf: &mut [u8]) -> HidResult<ReadOutcome> {
+        let mut size = read_with(&self.fd, |fd| unistd::read(fd.as_raw_fd(), buf).map_err(BackendError::from))
             .await
-            .map_err(HidError::from)
+            .map_err(HidError::from)?;
+        if self.latest_only {
+            // The size of the kernel queue is fixed, so skip ahead to the newest report instead
+            loop {
+                match unistd::read(self.fd.get_ref().as_raw_fd(), buf) {
+                    Ok(0) | Err(Errno::EAGAIN) => break,
+                    Ok(len) => {
+                        size = len;
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(err) => return Err(BackendError::from(err).into())
+                }
+            }
+        }
+        let report_id = match self.numbered_input_reports {
+            true => buf.first().copied().and_then(NonZeroU8::new),
+            false => None
+        };
+        // The kernel silently truncates reports that don't fit into the buffer, so the real length has to come from the descriptor
+        let report_len = self
+            .descriptor
+            .report_size(ReportType::Input, report_id.map_or(0, NonZeroU8::get))
+            .map(|len| len + usize::from(report_id.is_some()))
+            .map_or(size, |len| len.max(size));
+        Ok(ReadOutcome {
+            copied: size,
+            report_len,
+            report_id
+        })
+    }
+
+    pub fn dropped_reports(&self) -> u64 {
+        // Overflows of the kernel queue are not reported, so only the reports skipped because of `latest_only` are counted
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn clear_input_reports(&self) -> HidResult<()> {
+        let mut buf = [0u8; HID_MAX_BUFFER_SIZE];
+        loop {
+            match unistd::read(self.fd.get_ref().as_raw_fd(), &mut buf) {
+                Ok(0) | Err(Errno::EAGAIN) => return Ok(()),
+                Ok(_) => continue,
+                Err(err) => return Err(BackendError::from(err).into())
+            }
+        }
+    }
+
+    pub async fn read_input_report_raw(&self, buf: &mut [u8]) -> HidResult<usize> {
+        // Reports are delivered without any modification
+        Ok(self.read_input_report(buf).await?.copied)
+    }
+
+    pub async fn get_input_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        ensure!(!buf.is_empty(), HidError::zero_sized_data());
+        let size = unsafe { hidraw_ioc_get_input(self.fd.get_ref().as_raw_fd(), buf) }.map_err(BackendError::from)?;
+        Ok(size as usize)
+    }
+
+    pub fn input_report_len(&self, report_id: u8) -> HidResult<usize> {
+        self.descriptor
+            .report_size(ReportType::Input, report_id)
+            .map(|size| size + 1)
+            .ok_or_else(|| HidError::custom(format!("Device has no input report with id {report_id}")))
+    }
+
+    pub fn max_input_report_len(&self) -> HidResult<usize> {
+        self.descriptor
+            .report_lengths(ReportType::Input)
+            .into_iter()
+            .map(|(_, len)| len)
+            .max()
+            .ok_or(HidError::custom("Device has no input reports"))
+    }
+
+    pub fn decode_input_report(&self, report: &[u8]) -> HidResult<Vec<(Usage, i64)>> {
+        self.descriptor.decode(ReportType::Input, self.numbered_input_reports, report)
+    }
+
+    pub async fn read_feature_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        ensure!(!buf.is_empty(), HidError::zero_sized_data());
+        let size = unsafe { hidraw_ioc_get_feature(self.fd.get_ref().as_raw_fd(), buf) }.map_err(BackendError::from)?;
+        Ok(size as usize)
+    }
+
+    pub async fn read_feature_report_timeout(&self, buf: &mut [u8], _timeout: Duration) -> HidResult<Option<usize>> {
+        // The ioctl blocks, but the kernel aborts control transfers to unresponsive devices on its own
+        self.read_feature_report(buf).await.map(Some)
+    }
+
+    pub async fn write_feature_report(&self, data: &[u8]) -> HidResult<()> {
+        ensure!(!data.is_empty(), HidError::zero_sized_data());
+        // The ioctl is declared as read-write, so it needs a mutable buffer
+        let mut buf = data.to_vec();
+        let size = unsafe { hidraw_ioc_set_feature(self.fd.get_ref().as_raw_fd(), &mut buf) }.map_err(BackendError::from)?;
+        debug_assert_eq!(size as usize, data.len());
+        Ok(())
+    }
+
+    pub fn feature_report_len(&self, report_id: u8) -> HidResult<usize> {
+        self.descriptor
+            .report_size(ReportType::Feature, report_id)
+            .map(|size| size + 1)
+            .ok_or_else(|| HidError::custom(format!("Device has no feature report with id {report_id}")))
+    }
+
+    pub fn report_descriptor(&self) -> HidResult<Vec<u8>> {
+        Ok(self.descriptor.as_bytes().to_vec())
+    }
+
+    pub fn feature_report_ids(&self) -> HidResult<Vec<(u8, usize)>> {
+        Ok(self.descriptor.report_lengths(ReportType::Feature))
+    }
+
+    pub fn input_report_ids(&self) -> HidResult<Vec<(u8, usize)>> {
+        Ok(self.descriptor.report_lengths(ReportType::Input))
+    }
+
+    pub fn output_report_ids(&self) -> HidResult<Vec<(u8, usize)>> {
+        Ok(self.descriptor.report_lengths(ReportType::Output))
+    }
+
+    pub fn usages(&self) -> HidResult<Vec<Usage>> {
+        Ok(self.descriptor.usages().map(Usage::from).collect())
+    }
+
+    pub fn collection_input_report_ids(&self, usage_page: u16, usage_id: u16) -> HidResult<Option<Vec<u8>>> {
+        Ok(Some(self.descriptor.collection_report_ids(ReportType::Input, usage_page, usage_id)))
+    }
+
+    pub fn serial_number(&self) -> HidResult<Option<String>> {
+        // The serial number is always available through the DeviceInfo
+        Ok(None)
+    }
+
+    pub async fn control_transfer(&self, request_type: u8, request: u8, value: u16, index: u16, data: &mut [u8]) -> HidResult<usize> {
+        let node = self
+            .usb_device
+            .as_ref()
+            .ok_or(HidError::custom("Control transfers are only supported for usb devices"))?;
+        let length = u16::try_from(data.len()).map_err(|_| HidError::custom("Control transfers are limited to 65535 bytes"))?;
+        let node = node.clone();
+        let mut buffer = data.to_vec();
+        // The ioctl blocks until the device answers or the transfer times out
+        let (result, buffer) = unblock(move || {
+            let run = || -> HidResult<usize> {
+                let fd: OwnedFd = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .custom_flags(OFlag::O_CLOEXEC.bits())
+                    .open(node)?
+                    .into();
+                let mut transfer = UsbCtrlTransfer {
+                    request_type,
+                    request,
+                    value,
+                    index,
+                    length,
+                    timeout: CONTROL_TRANSFER_TIMEOUT_MS,
+                    data: buffer.as_mut_ptr().cast()
+                };
+                let size = unsafe { usbdevfs_control(fd.as_raw_fd(), &mut transfer) }.map_err(BackendError::from)?;
+                Ok(size as usize)
+            };
+            (run(), buffer)
+        })
+        .await?;
+        data.copy_from_slice(&buffer);
+        result
+    }
+
+    pub async fn get_output_report(&self, _buf: &mut [u8]) -> HidResult<usize> {
+        Err(HidError::custom("Requesting output reports is not supported on this platform"))
+    }
+
+    pub async fn indexed_string(&self, index: u32) -> HidResult<Option<String>> {
+        let index = u8::try_from(index).map_err(|_| HidError::custom("String descriptor indices are limited to 255"))?;
+        ensure!(index != 0, HidError::custom("String descriptor 0 lists the supported languages and is not a string"));
+        // The first entry of string descriptor 0 is the language the other strings are requested in
+        let mut languages = [0u8; 4];
+        let size = self
+            .control_transfer(USB_DIR_IN, USB_REQ_GET_DESCRIPTOR, USB_DT_STRING << 8, 0, &mut languages)
+            .await?;
+        ensure!(size == languages.len(), HidError::custom("Device has no string descriptors"));
+        let language = u16::from_le_bytes([languages[2], languages[3]]);
+
+        let mut descriptor = [0u8; 255];
+        let size = self
+            .control_transfer(USB_DIR_IN, USB_REQ_GET_DESCRIPTOR, (USB_DT_STRING << 8) | index as u16, language, &mut descriptor)
+            .await?;
+        // The first two bytes are the length and the type of the descriptor, followed by UTF-16LE encoded text
+        let size = size.min(descriptor[0] as usize);
+        let text: Vec<u16> = descriptor[2.min(size)..size]
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        Ok(Some(String::from_utf16_lossy(&text)).filter(|s| !s.is_empty()))
     }
 
     pub async fn write_output_report(&self, data: &[u8]) -> HidResult<()> {
         ensure!(!data.is_empty(), HidError::zero_sized_data());
-        write_with(&self.fd, |fd| write(fd.as_raw_fd(), data).map_err(BackendError::from))
+        write_with(&self.fd, |fd| unistd::write(fd.as_raw_fd(), data).map_err(BackendError::from))
             .await
             .map_err(HidError::from)
             .map(|i| debug_assert_eq!(i, data.len()))
     }
+
+    pub fn close(self) -> HidResult<()> {
+        // Dropping an OwnedFd ignores errors, so the fd is closed manually
+        let fd = into_inner(self.fd)?;
+        unistd::close(fd.into_raw_fd()).map_err(BackendError::from)?;
+        Ok(())
+    }
+
+    pub async fn write_output_reports(&self, reports: &[&[u8]]) -> HidResult<()> {
+        // The fd is non-blocking, so consecutive reports are written right away until the kernel queue is full
+        for report in reports {
+            self.write_output_report(report).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn write_output_report_timeout(&self, buf: &[u8], timeout: Duration) -> HidResult<bool> {
+        // The write only happens once the fd is writable, so abandoning it never leaves a partial write behind
+        let write = async { Some(self.write_output_report(buf).await) };
+        let timeout = async {
+            Delay::new(timeout).await;
+            None
+        };
+        Ok(write.or(timeout).await.transpose()?.is_some())
+    }
 }
 
-pub async fn open(id: &BackendDeviceId, mode: AccessMode) -> HidResult<BackendDevice> {
+pub async fn open(id: &BackendDeviceId, mode: AccessMode, config: &ReadConfig) -> HidResult<BackendDevice> {
+    ensure!(!config.exclusive, HidError::custom("Exclusive access is not supported by hidraw"));
     let fd: OwnedFd = OpenOptions::new()
         .read(mode.readable())
         .write(mode.writeable())
         .custom_flags((OFlag::O_CLOEXEC | OFlag::O_NONBLOCK).bits())
-        .open(id)?
+        .open(id)
+        .map_err(map_open_error)?
         .into();
 
     let mut size = 0i32;
-    unsafe { hidraw_ioc_grdescsize(fd.as_raw_fd(), &mut size) }
-        .map_err(|e| HidError::custom(format!("ioctl(GRDESCSIZE) error for {:?}, not a HIDRAW device?: {}", id, e)))?;
-
-    Ok(BackendDevice { fd: AsyncFd::new(fd)? })
+    unsafe { hidraw_ioc_grdescsize(fd.as_raw_fd(), &mut size) }.map_err(|e| match e {
+        Errno::EACCES | Errno::EPERM => map_open_error(e.into()),
+        e => HidError::custom(format!("ioctl(GRDESCSIZE) error for {:?}, not a HIDRAW device?: {}", id, e))
+    })?;
+
+    let descriptor = match usize::try_from(size) {
+        Ok(size) if size <= HID_MAX_DESCRIPTOR_SIZE => {
+            let mut descriptor = RawReportDescriptor {
+                size: size as u32,
+                value: [0; HID_MAX_DESCRIPTOR_SIZE]
+            };
+            unsafe { hidraw_ioc_grdesc(fd.as_raw_fd(), &mut descriptor) }
+                .map_err(|e| HidError::custom(format!("ioctl(GRDESC) error for {:?}: {}", id, e)))?;
+            ReportDescriptor::from_slice(&descriptor.value[..descriptor.size as usize])?
+        }
+        _ => {
+            // The ioctl can't return descriptors that don't fit into its fixed size buffer, but sysfs has no such limit
+            log::debug!("Report descriptor of {id:?} is {size} bytes long, reading it from sysfs instead");
+            let name = id.file_name().ok_or(HidError::custom("Can't find dev name"))?;
+            read_report_descriptor(&Path::new("/sys/class/hidraw/").join(name))?
+        }
+    };
+    let numbered_input_reports = config.numbered_reports(|| {
+        descriptor
+            .report_lengths(ReportType::Input)
+            .iter()
+            .any(|(id, _)| *id != 0)
+    });
+
+    Ok(BackendDevice {
+        id: id.clone(),
+        hid_device: hid_device_syspath(id),
+        fd: AsyncFd::new(fd)?,
+        descriptor,
+        numbered_input_reports,
+        latest_only: config.latest_only,
+        dropped: AtomicU64::new(0),
+        usb_device: usb_device_node(id)
+    })
 }
 
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BackendPrivateData {
-    serial_number: Option<String>
+    serial_number: Option<String>,
+    physical_device_id: Option<String>
 }
 pub type BackendDeviceId = PathBuf;
 pub type BackendError = std::io::Error;
 
+fn map_open_error(err: BackendError) -> HidError {
+    match err.kind() {
+        std::io::ErrorKind::PermissionDenied => ErrorSource::PermissionDenied(err).into(),
+        _ => err.into()
+    }
+}
+
+pub fn raw_os_error(err: &BackendError) -> Option<i32> {
+    err.raw_os_error()
+}
+
 impl From<BackendError> for ErrorSource {
     fn from(value: BackendError) -> Self {
         ErrorSource::PlatformSpecific(value)
     }
 }
 
-#[cfg(all(feature = "async-io", feature = "tokio"))]
-compile_error!("Only tokio or async-io can be active at the same time");
-
-#[cfg(feature = "async-io")]
+#[cfg(all(feature = "async-io", not(feature = "tokio")))]
 mod async_api {
     use std::os::fd::OwnedFd;
     use async_io::Async;
 
     pub type AsyncFd = Async<OwnedFd>;
 
+    pub fn into_inner(inner: AsyncFd) -> std::io::Result<OwnedFd> {
+        inner.into_inner()
+    }
+
     pub async fn read_with<R>(inner: &AsyncFd, op: impl FnMut(&OwnedFd) -> std::io::Result<R>) -> std::io::Result<R> {
         inner.read_with(op).await
     }
@@ -188,13 +687,17 @@ mod async_api {
     }
 }
 
-#[cfg(feature = "tokio")]
+#[cfg(all(feature = "tokio", not(feature = "async-io")))]
 mod async_api {
     use std::os::fd::OwnedFd;
     use tokio::io::Interest;
 
     pub type AsyncFd = tokio::io::unix::AsyncFd<OwnedFd>;
 
+    pub fn into_inner(inner: AsyncFd) -> std::io::Result<OwnedFd> {
+        Ok(inner.into_inner())
+    }
+
     pub async fn read_with<R>(inner: &AsyncFd, op: impl FnMut(&OwnedFd) -> std::io::Result<R>) -> std::io::Result<R> {
         inner.async_io(Interest::READABLE, op).await
     }
@@ -204,6 +707,60 @@ mod async_api {
     }
 }
 
+/// Both runtimes are enabled (e.g. by different crates in the same dependency graph)
+///
+/// Devices that are opened from within a tokio runtime use tokio, all others use async-io.
+#[cfg(all(feature = "async-io", feature = "tokio"))]
+mod async_api {
+    use std::os::fd::OwnedFd;
+    use async_io::Async;
+    use tokio::io::Interest;
+    use tokio::runtime::Handle;
+
+    #[derive(Debug)]
+    pub enum AsyncFd {
+        AsyncIo(Async<OwnedFd>),
+        Tokio(tokio::io::unix::AsyncFd<OwnedFd>)
+    }
+
+    impl AsyncFd {
+        pub fn new(fd: OwnedFd) -> std::io::Result<Self> {
+            match Handle::try_current() {
+                Ok(_) => tokio::io::unix::AsyncFd::new(fd).map(Self::Tokio),
+                Err(_) => Async::new(fd).map(Self::AsyncIo)
+            }
+        }
+
+        pub fn get_ref(&self) -> &OwnedFd {
+            match self {
+                AsyncFd::AsyncIo(fd) => fd.get_ref(),
+                AsyncFd::Tokio(fd) => fd.get_ref()
+            }
+        }
+    }
+
+    pub fn into_inner(inner: AsyncFd) -> std::io::Result<OwnedFd> {
+        match inner {
+            AsyncFd::AsyncIo(fd) => fd.into_inner(),
+            AsyncFd::Tokio(fd) => Ok(fd.into_inner())
+        }
+    }
+
+    pub async fn read_with<R>(inner: &AsyncFd, op: impl FnMut(&OwnedFd) -> std::io::Result<R>) -> std::io::Result<R> {
+        match inner {
+            AsyncFd::AsyncIo(fd) => fd.read_with(op).await,
+            AsyncFd::Tokio(fd) => fd.async_io(Interest::READABLE, op).await
+        }
+    }
+
+    pub async fn write_with<R>(inner: &AsyncFd, op: impl FnMut(&OwnedFd) -> std::io::Result<R>) -> std::io::Result<R> {
+        match inner {
+            AsyncFd::AsyncIo(fd) => fd.write_with(op).await,
+            AsyncFd::Tokio(fd) => fd.async_io(Interest::WRITABLE, op).await
+        }
+    }
+}
+
 /*
 udev device searching
 
@@ -269,3 +826,48 @@ fn get_device_info(raw_device: Device) -> HidResult<Vec<DeviceInfo>> {
   Ok(results)
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `/sys/class/hidraw/hidraw3/uevent` of a Bluetooth LE gamepad
+    const BLE_HIDRAW_UEVENT: &str = "MAJOR=241\nMINOR=3\nDEVNAME=hidraw3\n";
+
+    /// `/sys/class/hidraw/hidraw3/device/uevent` of the same gamepad, which BlueZ created through uhid
+    const BLE_HID_UEVENT: &str = "DRIVER=microsoft\n\
+        HID_ID=0005:0000045E:00000B13\n\
+        HID_NAME=Xbox Wireless Controller\n\
+        HID_PHYS=5c:f3:70:8b:a1:2d\n\
+        HID_UNIQ=44:16:22:9e:0b:71\n\
+        MODALIAS=hid:b0005g0001v0000045Ep00000B13\n";
+
+    #[test]
+    fn ble_uevent() {
+        let dev_name = read_property(BLE_HIDRAW_UEVENT, "DEVNAME").unwrap();
+        assert_eq!(mange_dev_name(dev_name).unwrap(), Path::new("/dev/hidraw3"));
+
+        let (bus, vendor_id, product_id) = read_property(BLE_HID_UEVENT, "HID_ID")
+            .and_then(parse_hid_vid_pid)
+            .unwrap();
+        assert_eq!((bus, vendor_id, product_id), (0x05, 0x045E, 0x0B13));
+        assert_eq!(parse_bus_type(bus), BusType::Bluetooth);
+        assert_eq!(read_property(BLE_HID_UEVENT, "HID_NAME"), Some("Xbox Wireless Controller"));
+        // The MAC address takes the place of the serial number
+        assert_eq!(read_property(BLE_HID_UEVENT, "HID_UNIQ"), Some("44:16:22:9e:0b:71"));
+    }
+
+    #[test]
+    fn malformed_hid_id() {
+        // A broken field must not shift the product id into the place of the vendor id
+        assert_eq!(parse_hid_vid_pid("0005:XYZ:00000B13"), None);
+        assert_eq!(parse_hid_vid_pid("0005:0001045E:00000B13"), None);
+        assert_eq!(parse_hid_vid_pid("0005:0000045E"), None);
+    }
+
+    #[test]
+    fn missing_properties() {
+        assert_eq!(read_property("MAJOR=241\nMINOR=3\n", "DEVNAME"), None);
+        assert_eq!(read_property(BLE_HID_UEVENT, "HID_SERIAL"), None);
+    }
+}
@@ -2,6 +2,9 @@ use std::ffi::c_void;
 use std::mem::ManuallyDrop;
 use std::ptr::null_mut;
 use std::slice::from_raw_parts;
+use std::time::Duration;
+
+use async_channel::{bounded, Receiver, Sender};
 
 use core_foundation::base::{kCFAllocatorDefault, CFIndex, CFRelease, CFType, TCFType};
 use core_foundation::number::CFNumber;
@@ -9,14 +12,14 @@ use core_foundation::runloop::CFRunLoop;
 use core_foundation::string::CFString;
 use core_foundation::{impl_TCFType, ConcreteCFType};
 use io_kit_sys::hid::base::{IOHIDDeviceRef, IOHIDReportCallback};
-use io_kit_sys::hid::device::{IOHIDDeviceClose, IOHIDDeviceCreate, IOHIDDeviceGetProperty, IOHIDDeviceGetTypeID, IOHIDDeviceOpen, IOHIDDeviceScheduleWithRunLoop, IOHIDDeviceSetReport, IOHIDDeviceUnscheduleFromRunLoop};
+use io_kit_sys::hid::device::{IOHIDDeviceClose, IOHIDDeviceCreate, IOHIDDeviceGetProperty, IOHIDDeviceGetReport, IOHIDDeviceGetReportWithCallback, IOHIDDeviceGetTypeID, IOHIDDeviceOpen, IOHIDDeviceScheduleWithRunLoop, IOHIDDeviceSetReport, IOHIDDeviceUnscheduleFromRunLoop};
 use io_kit_sys::hid::keys::{kIOHIDMaxInputReportSizeKey, IOHIDReportType};
-use io_kit_sys::ret::{kIOReturnSuccess, IOReturn};
+use io_kit_sys::ret::{kIOReturnNotPermitted, kIOReturnSuccess, kIOReturnTimeout, IOReturn};
 use io_kit_sys::types::IOOptionBits;
 
 use crate::backend::iohidmanager::service::{IOService, RegistryEntryId};
 use crate::backend::iohidmanager::utils::Key;
-use crate::{ensure, HidError, HidResult};
+use crate::{ensure, ErrorSource, HidError, HidResult};
 
 extern "C" {
     // Workaround for https://github.com/jtakakura/io-kit-rs/issues/6
@@ -85,20 +88,14 @@ impl IOHIDDevice {
 
     pub fn open(&self, options: IOOptionBits) -> HidResult<()> {
         let ret = unsafe { IOHIDDeviceOpen(self.as_concrete_TypeRef(), options) };
-        //TODO check for kIOReturnNotPermitted
-        ensure!(
-            ret == kIOReturnSuccess,
-            HidError::custom(format!("failed to open IOHIDDevice: {:?}", ret))
-        );
+        ensure!(ret != kIOReturnNotPermitted, HidError::from(ErrorSource::PermissionDenied(ret)));
+        ensure!(ret == kIOReturnSuccess, HidError::from(ret));
         Ok(())
     }
 
     pub fn close(&self, options: IOOptionBits) -> HidResult<()> {
         let ret = unsafe { IOHIDDeviceClose(self.as_concrete_TypeRef(), options) };
-        ensure!(
-            ret == kIOReturnSuccess,
-            HidError::custom(format!("failed to close IOHIDDevice: {:?}", ret))
-        );
+        ensure!(ret == kIOReturnSuccess, HidError::from(ret));
         Ok(())
     }
 
@@ -116,10 +113,50 @@ impl IOHIDDevice {
 
     pub fn set_report(&self, report_type: IOHIDReportType, report_id: CFIndex, report: &[u8]) -> HidResult<()> {
         let ret = unsafe { IOHIDDeviceSetReport(self.as_concrete_TypeRef(), report_type, report_id, report.as_ptr(), report.len() as _) };
-        ensure!(ret == kIOReturnSuccess, HidError::custom(format!("Failed to send report: {}", ret)));
+        ensure!(ret == kIOReturnSuccess, HidError::from(ret));
         Ok(())
     }
 
+    pub fn get_report(&self, report_type: IOHIDReportType, report_id: CFIndex, report: &mut [u8]) -> HidResult<usize> {
+        let mut length = report.len() as CFIndex;
+        let ret = unsafe { IOHIDDeviceGetReport(self.as_concrete_TypeRef(), report_type, report_id, report.as_mut_ptr(), &mut length) };
+        ensure!(ret == kIOReturnSuccess, HidError::from(ret));
+        Ok(length as usize)
+    }
+
+    /// Requests a report without blocking the calling thread
+    ///
+    /// The response is delivered through the returned channel once the device answered or `timeout` elapsed, in which case `None` is sent.
+    /// The callback is only invoked if the device is scheduled on a run loop.
+    pub fn get_report_with_callback(
+        &self, report_type: IOHIDReportType, report_id: CFIndex, length: usize, timeout: Duration
+    ) -> HidResult<Receiver<HidResult<Option<Vec<u8>>>>> {
+        let (sender, receiver) = bounded(1);
+        // The context owns the buffer, so it stays valid even if the receiver is dropped before the callback fires
+        let context = Box::into_raw(Box::new(GetReportContext {
+            buffer: vec![0u8; length],
+            length: length as CFIndex,
+            sender
+        }));
+        let ret = unsafe {
+            IOHIDDeviceGetReportWithCallback(
+                self.as_concrete_TypeRef(),
+                report_type,
+                report_id,
+                (*context).buffer.as_mut_ptr(),
+                &mut (*context).length,
+                timeout.as_secs_f64(),
+                get_report_callback,
+                context as _
+            )
+        };
+        if ret != kIOReturnSuccess {
+            drop(unsafe { Box::from_raw(context) });
+            return Err(HidError::from(ret));
+        }
+        Ok(receiver)
+    }
+
     pub fn register_input_report_callback<F>(&self, callback: F) -> HidResult<CallbackGuard>
         where
             F: FnMut(&[u8]) + Send + Sync + 'static
@@ -189,4 +226,26 @@ unsafe extern "C" fn hid_report_callback(
     let callback: &mut InputReportCallback = &mut *(context as *mut InputReportCallback);
     let data = from_raw_parts(report, report_length as usize);
     callback(data);
-}
\ No newline at end of file
+}
+
+struct GetReportContext {
+    buffer: Vec<u8>,
+    length: CFIndex,
+    sender: Sender<HidResult<Option<Vec<u8>>>>
+}
+
+unsafe extern "C" fn get_report_callback(
+    context: *mut c_void, result: IOReturn, _sender: *mut c_void, _report_type: IOHIDReportType, _report_id: u32, _report: *mut u8,
+    report_length: CFIndex
+) {
+    let GetReportContext { mut buffer, sender, .. } = *Box::from_raw(context as *mut GetReportContext);
+    let response = if result == kIOReturnSuccess {
+        buffer.truncate(report_length as usize);
+        Ok(Some(buffer))
+    } else if result == kIOReturnTimeout {
+        Ok(None)
+    } else {
+        Err(HidError::from(result))
+    };
+    let _ = sender.try_send(response);
+}
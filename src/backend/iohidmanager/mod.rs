@@ -4,17 +4,22 @@ mod runloop;
 mod service;
 mod utils;
 
+use std::num::NonZeroU8;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use async_channel::{bounded, Receiver, TrySendError};
+use async_channel::{bounded, unbounded, Receiver, TrySendError};
 use bytes::{BufMut, Bytes, BytesMut};
 use core_foundation::array::CFArray;
 use core_foundation::base::TCFType;
+use core_foundation::data::CFData;
 use core_foundation::dictionary::CFDictionary;
 use core_foundation::runloop::{kCFRunLoopDefaultMode, CFRunLoop};
 use core_foundation::string::CFString;
 use futures_core::Stream;
 use io_kit_sys::hid::keys::*;
+use io_kit_sys::ret::{kIOReturnExclusiveAccess, IOReturn};
 use io_kit_sys::types::IOOptionBits;
 
 use crate::backend::iohidmanager::device::{CallbackGuard, IOHIDDevice};
@@ -22,41 +27,97 @@ use crate::backend::iohidmanager::manager::IOHIDManager;
 use crate::backend::iohidmanager::runloop::RunLoop;
 use crate::backend::iohidmanager::service::{IOService, RegistryEntryId};
 use crate::backend::iohidmanager::utils::{iter, CFDictionaryExt};
-use crate::{ensure, AccessMode, DeviceInfo, ErrorSource, HidError, HidResult, SerialNumberExt};
+use crate::descriptor::ReportDescriptor;
+use crate::filter::any_matches_ids;
+use crate::{ensure, AccessMode, AccessState, BackendType, BusType, DeviceFilter, DeviceInfo, EnumerateOptions, EnumerationError, ErrorSource, HidError, HidResult, OverflowPolicy, ReadConfig, ReadOutcome, ReportType, SerialNumberExt, Usage};
 
-pub async fn enumerate() -> HidResult<impl Stream<Item = DeviceInfo> + Send + Unpin> {
+pub const BACKEND_TYPE: BackendType = BackendType::IoHidManager;
+
+pub async fn enumerate(
+    _options: EnumerateOptions, filters: &[DeviceFilter]
+) -> HidResult<impl Stream<Item = Result<DeviceInfo, EnumerationError>> + Send + Unpin> {
     let mut manager = IOHIDManager::new()?;
+    let filters = filters.to_vec();
     let devices = manager
         .get_devices()?
         .into_iter()
-        .map(get_device_infos)
-        .filter_map(|r| {
-            r.map_err(|e| log::trace!("Failed to query device information\n\tbecause {e:?}"))
-                .ok()
-        })
-        .flatten();
+        .flat_map(move |device| {
+            let description = IOService::try_from(&device)
+                .and_then(|service| service.path())
+                .unwrap_or_else(|_| format!("{device:?}"));
+            match get_device_infos(device, &filters) {
+                Ok(infos) => infos.into_iter().map(Ok).collect(),
+                Err(error) => vec![Err(EnumerationError { device: description, error })]
+            }
+        });
 
     Ok(iter(devices))
 }
 
-fn get_device_infos(device: IOHIDDevice) -> HidResult<Vec<DeviceInfo>> {
-    let primary_usage_page = device.get_i32_property(kIOHIDPrimaryUsagePageKey)? as u16;
-    let primary_usage = device.get_i32_property(kIOHIDPrimaryUsageKey)? as u16;
+pub fn clear_enumeration_cache() {
+    // Enumeration doesn't open any devices, so there is nothing to cache
+}
+
+pub async fn get_device_info(id: &BackendDeviceId) -> HidResult<DeviceInfo> {
+    let device = IOHIDDevice::try_from(*id)?;
+    // The info of the primary usage is always the last one
+    get_device_infos(device, &[])?
+        .pop()
+        .ok_or(HidError::custom("Device has no usages"))
+}
+
+fn get_device_infos(device: IOHIDDevice, filters: &[DeviceFilter]) -> HidResult<Vec<DeviceInfo>> {
     let vendor_id = device.get_i32_property(kIOHIDVendorIDKey)? as u16;
     let product_id = device.get_i32_property(kIOHIDProductIDKey)? as u16;
-    let serial_number = device.get_string_property(kIOHIDProductKey).ok();
+    if !any_matches_ids(filters, vendor_id, product_id) {
+        return Ok(Vec::new());
+    }
+    let primary_usage_page = device.get_i32_property(kIOHIDPrimaryUsagePageKey)? as u16;
+    let primary_usage = device.get_i32_property(kIOHIDPrimaryUsageKey)? as u16;
+    let serial_number = device
+        .get_string_property(kIOHIDSerialNumberKey)
+        .ok()
+        .filter(|s| !s.is_empty());
     let name = device.get_string_property(kIOHIDProductKey)?;
+    let manufacturer = device
+        .get_string_property(kIOHIDManufacturerKey)
+        .ok()
+        .filter(|s| !s.is_empty());
     let id = IOService::try_from(&device).and_then(|i| i.get_registry_entry_id())?;
+    let polling_interval = device
+        .get_i32_property(kIOHIDReportIntervalKey)
+        .ok()
+        .and_then(|us| u64::try_from(us).ok())
+        .map(Duration::from_micros);
+    // All interfaces of a USB device share the location id of the device
+    let physical_device_id = device
+        .get_i32_property(kIOHIDLocationIDKey)
+        .ok()
+        .map(|location| format!("{:08x}", location as u32));
+    let release_number = device.get_i32_property(kIOHIDVersionNumberKey).unwrap_or(0) as u16;
+    let bus_type = match device.get_string_property(kIOHIDTransportKey) {
+        Ok(transport) if transport == "USB" => BusType::Usb,
+        // Bluetooth LE devices report "Bluetooth Low Energy" or "BluetoothLowEnergy"
+        Ok(transport) if transport.starts_with("Bluetooth") => BusType::Bluetooth,
+        Ok(transport) if transport == "I2C" => BusType::I2c,
+        Ok(transport) if transport == "SPI" => BusType::Spi,
+        _ => BusType::Unknown
+    };
 
     let info = DeviceInfo {
         id: id.into(),
         name,
+        manufacturer,
         product_id,
         vendor_id,
         usage_id: primary_usage,
         usage_page: primary_usage_page,
+        polling_interval,
+        bus_type,
+        release_number,
         private_data: BackendPrivateData {
-            serial_number
+            serial_number,
+            physical_device_id
         }
     };
 
@@ -83,24 +144,71 @@ fn get_device_infos(device: IOHIDDevice) -> HidResult<Vec<DeviceInfo>> {
     Ok(results)
 }
 
+pub fn parse_device_id(id: &str) -> HidResult<BackendDeviceId> {
+    id.parse::<u64>()
+        .map(RegistryEntryId::from)
+        .map_err(|_| HidError::custom(format!("{id} is not a valid registry entry id")))
+}
+
+pub fn is_connected(id: &BackendDeviceId) -> bool {
+    IOService::try_from(*id).is_ok()
+}
+
+pub fn access_state(id: &BackendDeviceId) -> HidResult<AccessState> {
+    let device = IOHIDDevice::try_from(*id)?;
+    match device.open(kIOHIDOptionsTypeNone) {
+        Ok(()) => {
+            device.close(kIOHIDOptionsTypeNone)?;
+            Ok(AccessState::Available)
+        }
+        Err(err) if err.is_permission_denied() => Ok(AccessState::PermissionDenied),
+        Err(err) if err.raw_os_error() == Some(kIOReturnExclusiveAccess) => Ok(AccessState::BusyExclusive),
+        Err(err) => Err(err)
+    }
+}
+
+pub fn location(id: &BackendDeviceId) -> Option<String> {
+    IOService::try_from(*id)
+        .and_then(|service| service.path())
+        .map_err(|err| log::trace!("Failed to query the location of {id}\n\t{err:?}"))
+        .ok()
+}
+
+pub fn format_device_id(id: &BackendDeviceId) -> String {
+    id.to_string()
+}
+
 struct InputReceiver {
     run_loop: Arc<RunLoop>,
     _callback: CallbackGuard,
-    read_channel: Receiver<Bytes>
+    read_channel: Receiver<Bytes>,
+    dropped: Arc<AtomicU64>
 }
 
 impl InputReceiver {
-    async fn new(device: &IOHIDDevice) -> HidResult<Self> {
+    async fn new(device: &IOHIDDevice, label: String, config: &ReadConfig) -> HidResult<Self> {
         let mut byte_buffer = BytesMut::with_capacity(1024);
-        let (sender, receiver) = bounded(64);
+        let limit = config.queue_limit();
+        let (sender, receiver) = match config.overflow {
+            OverflowPolicy::DropOldest => bounded(config.queue_capacity()),
+            OverflowPolicy::Grow(_) => unbounded()
+        };
 
         let drain = receiver.clone();
+        let dropped = Arc::new(AtomicU64::new(0));
+        let counter = dropped.clone();
         let callback = device.register_input_report_callback(move |report| {
             byte_buffer.put(report);
             let mut bytes = byte_buffer.split().freeze();
+            if sender.len() >= limit && drain.try_recv().is_ok() {
+                log::trace!("[{label}] Dropping previous input report because the queue is full");
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
             while let Err(TrySendError::Full(ret)) = sender.try_send(bytes) {
-                log::trace!("Dropping previous input report because the queue is full");
-                let _ = drain.try_recv();
+                log::trace!("[{label}] Dropping previous input report because the queue is full");
+                if drain.try_recv().is_ok() {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
                 bytes = ret;
             }
         })?;
@@ -110,16 +218,20 @@ impl InputReceiver {
         Ok(Self {
             run_loop,
             _callback: callback,
-            read_channel: receiver
+            read_channel: receiver,
+            dropped
         })
     }
 
-    fn stop(self, device: &IOHIDDevice) {
-        self.run_loop
-            .unschedule_device(device)
-            .unwrap_or_else(|_| log::warn!("Failed to unschedule IOHIDDevice from run loop"));
+    fn stop(self, device: &IOHIDDevice) -> HidResult<()> {
+        self.run_loop.unschedule_device(device)?;
         let default_mode = unsafe { CFString::wrap_under_create_rule(kCFRunLoopDefaultMode) };
         device.schedule_with_runloop(&CFRunLoop::get_main(), &default_mode);
+        Ok(())
+    }
+
+    fn clear(&self) {
+        while self.read_channel.try_recv().is_ok() {}
     }
 
     async fn recv(&self) -> HidResult<Bytes> {
@@ -131,42 +243,80 @@ impl InputReceiver {
 }
 
 pub struct BackendDevice {
+    id: BackendDeviceId,
     device: IOHIDDevice,
     open_options: IOOptionBits,
-    input_receiver: Option<InputReceiver>
+    input_receiver: Option<InputReceiver>,
+    numbered_input_reports: bool,
+    closed: bool
 }
 
 impl Drop for BackendDevice {
     fn drop(&mut self) {
-        if let Some(input) = self.input_receiver.take() {
-            input.stop(&self.device);
-        }
-        self.device
-            .close(self.open_options)
+        self.shutdown()
             .unwrap_or_else(|err| log::warn!("Failed to close IOHIDDevice\n\t{err:?}"));
     }
 }
 
-pub async fn open(id: &BackendDeviceId, mode: AccessMode) -> HidResult<BackendDevice> {
-    let open_options = 0;
+pub async fn open(id: &BackendDeviceId, mode: AccessMode, config: &ReadConfig) -> HidResult<BackendDevice> {
+    let open_options = match config.exclusive {
+        true => kIOHIDOptionsTypeSeizeDevice,
+        false => kIOHIDOptionsTypeNone
+    };
     let device = IOHIDDevice::try_from(*id)?;
     device.open(open_options)?;
 
     let input_receiver = if mode.readable() {
-        Some(InputReceiver::new(&device).await?)
+        Some(InputReceiver::new(&device, id.to_string(), config).await?)
     } else {
         None
     };
+    let numbered_input_reports = config.numbered_reports(|| {
+        device
+            .property::<CFData>(kIOHIDReportDescriptorKey)
+            .and_then(|descriptor| ReportDescriptor::from_slice(descriptor.bytes()))
+            .map(|descriptor| {
+                descriptor
+                    .report_lengths(ReportType::Input)
+                    .iter()
+                    .any(|(id, _)| *id != 0)
+            })
+            .unwrap_or(false)
+    });
 
     Ok(BackendDevice {
+        id: *id,
         device,
         open_options,
-        input_receiver
+        input_receiver,
+        numbered_input_reports,
+        closed: false
     })
 }
 
 impl BackendDevice {
-    pub async fn read_input_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+    pub fn is_connected(&self) -> bool {
+        is_connected(&self.id)
+    }
+
+    pub fn close(mut self) -> HidResult<()> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> HidResult<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.closed = true;
+        let stopped = match self.input_receiver.take() {
+            Some(input) => input.stop(&self.device),
+            None => Ok(())
+        };
+        let closed = self.device.close(self.open_options);
+        stopped.and(closed)
+    }
+
+    pub async fn read_input_report(&self, buf: &mut [u8]) -> HidResult<ReadOutcome> {
         ensure!(!buf.is_empty(), HidError::zero_sized_data());
         let bytes = self
             .input_receiver
@@ -176,7 +326,166 @@ impl BackendDevice {
             .await?;
         let length = bytes.len().min(buf.len());
         buf[..length].copy_from_slice(&bytes[..length]);
-        Ok(length)
+        let report_id = match self.numbered_input_reports {
+            true => bytes.first().copied().and_then(NonZeroU8::new),
+            false => None
+        };
+        Ok(ReadOutcome {
+            copied: length,
+            report_len: bytes.len(),
+            report_id
+        })
+    }
+
+    pub async fn read_input_report_raw(&self, buf: &mut [u8]) -> HidResult<usize> {
+        // Reports are delivered without any modification
+        Ok(self.read_input_report(buf).await?.copied)
+    }
+
+    pub async fn get_input_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        ensure!(!buf.is_empty(), HidError::zero_sized_data());
+
+        let report_id = buf[0];
+        let (data, offset) = if report_id == 0x0 { (&mut buf[1..], 1) } else { (buf, 0) };
+
+        let size = self.device.get_report(kIOHIDReportTypeInput, report_id as _, data)?;
+        Ok(size + offset)
+    }
+
+    pub fn input_report_len(&self, _report_id: u8) -> HidResult<usize> {
+        Ok(self.device.get_i32_property(kIOHIDMaxInputReportSizeKey)? as usize + 1)
+    }
+
+    pub fn max_input_report_len(&self) -> HidResult<usize> {
+        Ok(self.device.get_i32_property(kIOHIDMaxInputReportSizeKey)? as usize + 1)
+    }
+
+    pub fn decode_input_report(&self, report: &[u8]) -> HidResult<Vec<(Usage, i64)>> {
+        self.parsed_report_descriptor()?
+            .decode(ReportType::Input, self.numbered_input_reports, report)
+    }
+
+    pub fn serial_number(&self) -> HidResult<Option<String>> {
+        // The serial number is always available through the DeviceInfo
+        Ok(None)
+    }
+
+    pub async fn control_transfer(&self, _request_type: u8, _request: u8, _value: u16, _index: u16, _data: &mut [u8]) -> HidResult<usize> {
+        Err(HidError::custom("Control transfers are not supported on this platform"))
+    }
+
+    pub async fn get_output_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        ensure!(!buf.is_empty(), HidError::zero_sized_data());
+
+        let report_id = buf[0];
+        let (data, offset) = if report_id == 0x0 { (&mut buf[1..], 1) } else { (buf, 0) };
+
+        let size = self.device.get_report(kIOHIDReportTypeOutput, report_id as _, data)?;
+        Ok(size + offset)
+    }
+
+    pub async fn indexed_string(&self, _index: u32) -> HidResult<Option<String>> {
+        Err(HidError::custom("Indexed strings are not supported on this platform"))
+    }
+
+    pub async fn read_feature_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        ensure!(!buf.is_empty(), HidError::zero_sized_data());
+
+        let report_id = buf[0];
+        let (data, offset) = if report_id == 0x0 { (&mut buf[1..], 1) } else { (buf, 0) };
+
+        let size = self.device.get_report(kIOHIDReportTypeFeature, report_id as _, data)?;
+        Ok(size + offset)
+    }
+
+    pub async fn read_feature_report_timeout(&self, buf: &mut [u8], timeout: Duration) -> HidResult<Option<usize>> {
+        ensure!(!buf.is_empty(), HidError::zero_sized_data());
+        if self.input_receiver.is_none() {
+            // Write-only devices are not scheduled on a run loop, so the asynchronous callback would never fire
+            return self.read_feature_report(buf).await.map(Some);
+        }
+
+        let report_id = buf[0];
+        let (data, offset) = if report_id == 0x0 { (&mut buf[1..], 1) } else { (buf, 0) };
+
+        let response = self
+            .device
+            .get_report_with_callback(kIOHIDReportTypeFeature, report_id as _, data.len(), timeout)?
+            .recv()
+            .await
+            .map_err(|_| HidError::custom("Feature report callback got dropped unexpectedly"))??;
+        Ok(response.map(|report| {
+            let length = report.len().min(data.len());
+            data[..length].copy_from_slice(&report[..length]);
+            length + offset
+        }))
+    }
+
+    pub async fn write_feature_report(&self, buf: &[u8]) -> HidResult<()> {
+        ensure!(!buf.is_empty(), HidError::zero_sized_data());
+
+        let report_id = buf[0];
+        let data_to_send = if report_id == 0x0 { &buf[1..] } else { buf };
+
+        self.device.set_report(kIOHIDReportTypeFeature, report_id as _, data_to_send)
+    }
+
+    pub fn feature_report_len(&self, _report_id: u8) -> HidResult<usize> {
+        Ok(self.device.get_i32_property(kIOHIDMaxFeatureReportSizeKey)? as usize + 1)
+    }
+
+    pub fn report_descriptor(&self) -> HidResult<Vec<u8>> {
+        let descriptor = self.device.property::<CFData>(kIOHIDReportDescriptorKey)?;
+        Ok(descriptor.bytes().to_vec())
+    }
+
+    fn parsed_report_descriptor(&self) -> HidResult<ReportDescriptor> {
+        Ok(ReportDescriptor::from_vec(self.report_descriptor()?))
+    }
+
+    pub fn feature_report_ids(&self) -> HidResult<Vec<(u8, usize)>> {
+        Ok(self.parsed_report_descriptor()?.report_lengths(ReportType::Feature))
+    }
+
+    pub fn input_report_ids(&self) -> HidResult<Vec<(u8, usize)>> {
+        Ok(self.parsed_report_descriptor()?.report_lengths(ReportType::Input))
+    }
+
+    pub fn output_report_ids(&self) -> HidResult<Vec<(u8, usize)>> {
+        Ok(self.parsed_report_descriptor()?.report_lengths(ReportType::Output))
+    }
+
+    pub fn usages(&self) -> HidResult<Vec<Usage>> {
+        let usages = self
+            .device
+            .property::<CFArray>(kIOHIDDeviceUsagePairsKey)?
+            .iter()
+            .map(|i| unsafe { CFDictionary::wrap_under_get_rule(*i as _) })
+            .filter_map(|dict| {
+                let usage = dict.lookup_i32(kIOHIDDeviceUsageKey).ok()? as u16;
+                let usage_page = dict.lookup_i32(kIOHIDDeviceUsagePageKey).ok()? as u16;
+                Some(Usage::new(usage_page, usage))
+            })
+            .collect();
+        Ok(usages)
+    }
+
+    pub fn collection_input_report_ids(&self, usage_page: u16, usage_id: u16) -> HidResult<Option<Vec<u8>>> {
+        Ok(Some(self.parsed_report_descriptor()?.collection_report_ids(ReportType::Input, usage_page, usage_id)))
+    }
+
+    pub fn dropped_reports(&self) -> u64 {
+        self.input_receiver
+            .as_ref()
+            .map_or(0, |input| input.dropped.load(Ordering::Relaxed))
+    }
+
+    pub fn clear_input_reports(&self) -> HidResult<()> {
+        self.input_receiver
+            .as_ref()
+            .expect("InputReceiver not active")
+            .clear();
+        Ok(())
     }
 
     pub async fn write_output_report(&self, buf: &[u8]) -> HidResult<()> {
@@ -187,15 +496,34 @@ impl BackendDevice {
 
         self.device.set_report(1, report_id as _, data_to_send)
     }
+
+    pub async fn write_output_reports(&self, reports: &[&[u8]]) -> HidResult<()> {
+        // IOHIDDeviceSetReport is synchronous, so there is nothing to batch
+        for report in reports {
+            self.write_output_report(report).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn write_output_report_timeout(&self, buf: &[u8], _timeout: Duration) -> HidResult<bool> {
+        // IOHIDDeviceSetReport blocks, but IOKit gives up on unresponsive devices on its own
+        self.write_output_report(buf).await.map(|_| true)
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BackendPrivateData {
     serial_number: Option<String>,
+    physical_device_id: Option<String>
 }
 
 pub type BackendDeviceId = RegistryEntryId;
-pub type BackendError = ();
+pub type BackendError = IOReturn;
+
+pub fn raw_os_error(err: &BackendError) -> Option<i32> {
+    Some(*err)
+}
 
 impl From<BackendError> for ErrorSource {
     fn from(value: BackendError) -> Self {
@@ -205,9 +533,12 @@ impl From<BackendError> for ErrorSource {
 
 impl SerialNumberExt for DeviceInfo {
     fn serial_number(&self) -> Option<&str> {
-        self.private_data
-            .serial_number
-            .as_ref()
-            .map(String::as_str)
+        self.private_data.serial_number.as_deref()
+    }
+}
+
+impl DeviceInfo {
+    pub(crate) fn physical_device_id(&self) -> Option<&str> {
+        self.private_data.physical_device_id.as_deref()
     }
 }
\ No newline at end of file
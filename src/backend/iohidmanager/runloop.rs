@@ -13,10 +13,19 @@ use core_foundation::runloop::{
     CFRunLoop, CFRunLoopRunResult, CFRunLoopSource, CFRunLoopSourceContext, CFRunLoopSourceCreate, CFRunLoopSourceSignal, CFRunLoopWakeUp
 };
 use core_foundation::string::CFString;
+use futures_lite::FutureExt;
+use futures_timer::Delay;
 
 use crate::backend::iohidmanager::device::IOHIDDevice;
 use crate::{HidError, HidResult};
 
+/// How long to wait for a newly spawned run loop thread to report back before giving up
+///
+/// This is deliberately not configurable: the thread reports back right after creating its run loop source, which takes
+/// microseconds, and a thread that dies while starting drops its sender and fails the wait immediately. So the timeout only
+/// guards against a thread that never gets scheduled at all, where a generous fixed limit is as good as any user chosen value.
+const RUN_LOOP_START_TIMEOUT: Duration = Duration::from_secs(5);
+
 struct LoopSource(CFRunLoopSource, CFRunLoop);
 unsafe impl Send for LoopSource {}
 unsafe impl Sync for LoopSource {}
@@ -103,9 +112,10 @@ impl RunLoop {
                 source: LoopSource(source, CFRunLoop::get_current()),
                 sender: ext_sender
             };
-            sender
-                .try_send(ext_sender)
-                .unwrap_or_else(|_| panic!("Failed to send sender"));
+            if sender.try_send(ext_sender).is_err() {
+                log::warn!("Run loop was abandoned before it finished starting");
+                return;
+            }
             drop(sender);
 
             'outer: loop {
@@ -133,10 +143,18 @@ impl RunLoop {
             log::trace!("Stopping run loop");
         }));
 
-        let sender = receiver
-            .recv()
-            .await
-            .map_err(|_| HidError::custom("Run loop failed to start"))?;
+        let start = async {
+            receiver
+                .recv()
+                .await
+                .map_err(|_| HidError::custom("Run loop failed to start"))
+        };
+        let sender = start
+            .or(async {
+                Delay::new(RUN_LOOP_START_TIMEOUT).await;
+                Err(HidError::custom("Run loop did not start in time"))
+            })
+            .await?;
 
         Ok(Self { sender, thread })
     }
@@ -1,10 +1,15 @@
+use std::ffi::{c_char, CStr};
+use std::fmt::{Display, Formatter};
+
 use core_foundation::base::TCFType;
 use core_foundation::dictionary::CFMutableDictionaryRef;
 use io_kit_sys::hid::device::IOHIDDeviceGetService;
 use io_kit_sys::ret::kIOReturnSuccess;
+use io_kit_sys::keys::kIOServicePlane;
 use io_kit_sys::types::io_service_t;
 use io_kit_sys::{
-    kIOMasterPortDefault, IOObjectRelease, IOObjectRetain, IORegistryEntryGetRegistryEntryID, IORegistryEntryIDMatching, IOServiceGetMatchingService
+    kIOMasterPortDefault, IOObjectRelease, IOObjectRetain, IORegistryEntryGetPath, IORegistryEntryGetRegistryEntryID, IORegistryEntryIDMatching,
+    IOServiceGetMatchingService
 };
 use mach2::port::MACH_PORT_NULL;
 
@@ -46,6 +51,15 @@ impl IOService {
         Ok(IOService(self.0))
     }
 
+    /// The path of this service in the IOService plane (i.e. `IOService:/AppleACPIPlatformExpert/.../IOUSBHostDevice@14100000/...`)
+    pub fn path(&self) -> HidResult<String> {
+        // Matches the size of `io_string_t`
+        let mut path = [0 as c_char; 512];
+        let result = unsafe { IORegistryEntryGetPath(self.0, kIOServicePlane, path.as_mut_ptr()) };
+        ensure!(result == kIOReturnSuccess, HidError::custom("Failed to retrieve the service path"));
+        Ok(unsafe { CStr::from_ptr(path.as_ptr()) }.to_string_lossy().into_owned())
+    }
+
     pub fn get_registry_entry_id(&self) -> HidResult<RegistryEntryId> {
         let copy = self.duplicate()?;
         let mut entry_id = 0;
@@ -61,10 +75,28 @@ impl Drop for IOService {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 #[repr(transparent)]
 pub struct RegistryEntryId(u64);
 
+impl From<u64> for RegistryEntryId {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<RegistryEntryId> for u64 {
+    fn from(value: RegistryEntryId) -> Self {
+        value.0
+    }
+}
+
+impl Display for RegistryEntryId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl RegistryEntryId {
     fn matching(self) -> CFMutableDictionaryRef {
         unsafe { IORegistryEntryIDMatching(self.0) }
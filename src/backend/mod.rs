@@ -1,12 +1,30 @@
+#[cfg(target_os = "windows")]
+mod cfgmgr;
+#[cfg(target_os = "windows")]
+mod hidp;
+
 #[cfg(all(target_os = "windows", feature = "win32"))]
 mod win32;
 #[cfg(all(target_os = "windows", feature = "win32"))]
-pub use win32::{enumerate, open, BackendDevice, BackendDeviceId, BackendError, BackendPrivateData};
+pub use win32::{access_state, clear_enumeration_cache, BACKEND_TYPE, enumerate, format_device_id, get_device_info, is_connected, location, open, parse_device_id, raw_os_error, BackendDevice, BackendDeviceId, BackendError, BackendPrivateData};
 
 #[cfg(all(target_os = "windows", feature = "winrt"))]
 mod winrt;
 #[cfg(all(target_os = "windows", feature = "winrt"))]
-pub use winrt::{enumerate, open, BackendDevice, BackendDeviceId, BackendError, BackendPrivateData};
+pub use winrt::{access_state, clear_enumeration_cache, BACKEND_TYPE, enumerate, format_device_id, get_device_info, is_connected, location, open, parse_device_id, raw_os_error, BackendDevice, BackendDeviceId, BackendError, BackendPrivateData};
+
+/// Shortens a device interface path to the part that identifies the device, so it can be used to tell devices apart in log messages
+///
+/// For example, `\\?\hid#vid_046d&pid_c52b&mi_02&col01#8&2a3b4c&0&0001#{4d1e55b2-f16f-11cf-88cb-001111000030}`
+/// becomes `vid_046d&pid_c52b&mi_02&col01#8&2a3b4c&0&0001`.
+#[cfg(target_os = "windows")]
+pub(crate) fn interface_label(path: &str) -> String {
+    let parts: Vec<&str> = path.split('#').collect();
+    match parts.as_slice() {
+        [_, hardware_id, instance, ..] => format!("{hardware_id}#{instance}"),
+        _ => path.to_string()
+    }
+}
 
 #[cfg(all(feature = "win32", feature = "winrt"))]
 compile_error!("Only win32 or winrt can be active at the same time");
@@ -15,10 +33,10 @@ compile_error!("Only win32 or winrt can be active at the same time");
 #[cfg(target_os = "linux")]
 mod hidraw;
 #[cfg(target_os = "linux")]
-pub use hidraw::{enumerate, open, BackendDevice, BackendDeviceId, BackendError, BackendPrivateData};
+pub use hidraw::{access_state, clear_enumeration_cache, BACKEND_TYPE, enumerate, format_device_id, get_device_info, is_connected, location, open, parse_device_id, raw_os_error, BackendDevice, BackendDeviceId, BackendError, BackendPrivateData};
 
 
 #[cfg(target_os = "macos")]
 mod iohidmanager;
 #[cfg(target_os = "macos")]
-pub use iohidmanager::{enumerate, open, BackendDevice, BackendDeviceId, BackendError, BackendPrivateData};
+pub use iohidmanager::{access_state, clear_enumeration_cache, BACKEND_TYPE, enumerate, format_device_id, get_device_info, is_connected, location, open, parse_device_id, raw_os_error, BackendDevice, BackendDeviceId, BackendError, BackendPrivateData};
@@ -1,16 +1,21 @@
 use std::fmt::{Debug, Formatter};
 use std::marker::PhantomData;
 use std::mem::{forget, take};
+use std::num::NonZeroU8;
 use std::sync::{Arc};
+use std::time::Duration;
+use futures_lite::FutureExt;
+use futures_timer::Delay;
 use log::{debug, error, trace, warn};
 use windows::core::HRESULT;
-use windows::Win32::Foundation::{CloseHandle, ERROR_IO_INCOMPLETE, ERROR_IO_PENDING, ERROR_NOT_FOUND};
+use windows::Win32::Devices::HumanInterfaceDevice::HidD_FlushQueue;
+use windows::Win32::Foundation::{CloseHandle, ERROR_IO_INCOMPLETE, ERROR_IO_PENDING, ERROR_NOT_FOUND, ERROR_OPERATION_ABORTED};
 use windows::Win32::Storage::FileSystem::{ReadFile, WriteFile};
 use windows::Win32::System::IO::{CancelIoEx, GetOverlappedResult, OVERLAPPED};
 use windows::Win32::System::Threading::CreateEventW;
 use crate::backend::win32::device::Device;
 use crate::backend::win32::waiter::WaitableHandleFuture;
-use crate::HidResult;
+use crate::{HidResult, ReadOutcome};
 
 #[derive(Debug)]
 pub struct Readable;
@@ -20,6 +25,7 @@ pub struct Writable;
 
 pub struct IoBuffer<T> {
     device: Arc<Device>,
+    label: Arc<str>,
     buffer: Box<[u8]>,
     overlapped: Box<Overlapped>,
     pending: bool,
@@ -29,15 +35,18 @@ pub struct IoBuffer<T> {
 impl<T> Debug for IoBuffer<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("IoBuffer")
+            .field("label", &self.label)
             .field("pending", &self.pending)
             .finish_non_exhaustive()
     }
 }
 
 impl<T> IoBuffer<T> {
-    pub fn new(device: Arc<Device>, size: usize) -> HidResult<Self> {
+    /// Creates a new buffer for I/O operations of up to `size` bytes, `label` identifies the device in log messages
+    pub fn new(device: Arc<Device>, label: Arc<str>, size: usize) -> HidResult<Self> {
         Ok(IoBuffer {
             device,
+            label,
             buffer: vec![0; size].into_boxed_slice(),
             overlapped: Box::new(Overlapped::new()?),
             pending: false,
@@ -56,19 +65,66 @@ impl<T> IoBuffer<T> {
                 self.pending = true;
             }
             Err(err) => {
-                if let Err(err) = self.cancel_io() {
-                    self.pending = true;
-                    panic!("Failed to cancel I/O operation: {:?}", err);
-                } else {
-                    self.pending = false;
-                }
+                // The operation failed synchronously, so nothing was queued that could be canceled or waited for
+                self.pending = false;
                 return Err(err.into());
             }
         }
         Ok(())
     }
 
+    /// Cancels the pending I/O operation and waits until the kernel no longer uses the buffer and overlapped structure
+    ///
+    /// The operation might complete concurrently with the cancellation request, in which case `CancelIoEx` fails with `ERROR_NOT_FOUND`.
+    /// Either way, the buffer must not be reused or freed before `GetOverlappedResult` has observed the completion.
+    /// Without a pending operation the event never gets signaled, so the blocking wait is skipped in that case.
     fn cancel_io(&mut self) -> HidResult<()> {
+        if !self.pending {
+            return Ok(());
+        }
+        self.request_cancel()?;
+        let mut bytes_transferred = 0;
+        let result = unsafe {
+            GetOverlappedResult(
+                self.device.handle(),
+                self.overlapped.as_raw(),
+                &mut bytes_transferred,
+                true
+            )
+        };
+        match result {
+            Ok(()) => trace!("[{}] I/O operation completed before it could be canceled (transferred {} bytes)", self.label, bytes_transferred),
+            Err(err) if err.code() == HRESULT::from_win32(ERROR_OPERATION_ABORTED.0) => trace!("[{}] Canceled I/O operation", self.label),
+            Err(err) => debug!("[{}] Canceled I/O operation failed: {err}", self.label)
+        }
+        Ok(())
+    }
+
+    /// Like [Self::cancel_io], but waits for the cancellation without blocking the thread
+    ///
+    /// If this future gets dropped before the cancellation completed, the operation is still pending and dropping the buffer waits for it.
+    async fn cancel_io_async(&mut self) -> HidResult<()> {
+        if !self.pending {
+            return Ok(());
+        }
+        self.request_cancel()?;
+        loop {
+            match self.get_result() {
+                Ok(Some(size)) => trace!("[{}] I/O operation completed before it could be canceled (transferred {} bytes)", self.label, size),
+                Ok(None) => {
+                    self.overlapped.wait_for_completion().await?;
+                    continue;
+                }
+                Err(err) if err.raw_os_error() == Some(ERROR_OPERATION_ABORTED.to_hresult().0) => trace!("[{}] Canceled I/O operation", self.label),
+                Err(err) => debug!("[{}] Canceled I/O operation failed: {err}", self.label)
+            }
+            self.pending = false;
+            return Ok(());
+        }
+    }
+
+    /// Asks the kernel to cancel the pending I/O operation, which might have completed already
+    fn request_cancel(&self) -> HidResult<()> {
         match unsafe { CancelIoEx(self.device.handle(), Some(self.overlapped.as_raw())) } {
             Ok(()) => Ok(()),
             Err(err) if err.code() == HRESULT::from_win32(ERROR_NOT_FOUND.0) => Ok(()),
@@ -76,6 +132,22 @@ impl<T> IoBuffer<T> {
         }
     }
 
+    /// Like dropping the buffer, but returns an error instead of panicking if the pending I/O operation can't be canceled
+    pub fn close(mut self) -> HidResult<()> {
+        if self.pending {
+            trace!("[{}] Canceling pending I/O operation", self.label);
+            if let Err(err) = self.cancel_io() {
+                // SAFETY: Same as in `drop`, the buffer and overlapped structures must stay valid for the still running operation
+                forget(take(&mut self.buffer));
+                forget(take(&mut self.overlapped));
+                self.pending = false;
+                return Err(err);
+            }
+            self.pending = false;
+        }
+        Ok(())
+    }
+
     fn get_result(&mut self) -> HidResult<Option<usize>> {
         let mut bytes_transferred = 0;
         let result = unsafe {
@@ -98,7 +170,7 @@ impl<T> IoBuffer<T> {
 impl<T> Drop for IoBuffer<T> {
     fn drop(&mut self) {
         if self.pending {
-            trace!("Canceling pending I/O operation");
+            trace!("[{}] Canceling pending I/O operation", self.label);
             if let Err(err) = self.cancel_io() {
                 // SAFETY: If canceling the I/O operation fails, the buffer and overlapped structures are leaked before we panic to make sure they stay valid even after `Self` gets freed.
                 forget(take(&mut self.buffer));
@@ -112,8 +184,8 @@ impl<T> Drop for IoBuffer<T> {
 impl IoBuffer<Readable> {
 
     fn start_read(&mut self) -> HidResult<()> {
+        trace!("[{}] Starting new read operation", self.label);
         self.start_io(|device, buffer, overlapped| unsafe {
-            trace!("Starting new read operation");
             ReadFile(
                 device.handle(),
                 Some(buffer),
@@ -123,31 +195,49 @@ impl IoBuffer<Readable> {
         })
     }
 
-    pub async fn read(&mut self, buf: &mut[u8]) -> HidResult<usize> {
+    /// Reads the next input report into `buf`, optionally removing the leading report id byte that Windows adds to unnumbered reports
+    pub async fn read(&mut self, buf: &mut[u8], strip_report_id: bool) -> HidResult<ReadOutcome> {
         loop {
             match self.pending {
                 false => self.start_read()?,
                 true => match self.get_result()?{
                     Some(size) => {
-                        trace!("Completed read operation (retrieved {} bytes)", size);
+                        trace!("[{}] Completed read operation (retrieved {} bytes)", self.label, size);
                         let mut data = &self.buffer[..size];
-                        if data[0] == 0x0 {
+                        let mut report_id = NonZeroU8::new(data[0]);
+                        if strip_report_id {
                             data = &data[1..];
+                            report_id = None;
                         }
                         let mut copy_len = data.len();
                         if copy_len > buf.len() {
-                            debug!("Input report ({}) is larger than the provided buffer ({}), truncating data", copy_len, buf.len());
+                            debug!("[{}] Input report ({}) is larger than the provided buffer ({}), truncating data", self.label, copy_len, buf.len());
                             copy_len = buf.len();
                         }
                         buf[..copy_len].copy_from_slice(&data[..copy_len]);
                         self.pending = false;
-                        return Ok(copy_len);
+                        return Ok(ReadOutcome {
+                            copied: copy_len,
+                            report_len: data.len(),
+                            report_id
+                        });
                     },
                     None => self.overlapped.wait_for_completion().await?,
                 }
             }
         }
     }
+
+    pub fn clear(&mut self) -> HidResult<()> {
+        if self.pending && self.get_result()?.is_some() {
+            trace!("[{}] Discarding completed read operation", self.label);
+            self.pending = false;
+        }
+        unsafe {
+            HidD_FlushQueue(self.device.handle()).ok()?;
+        }
+        Ok(())
+    }
 }
 
 impl IoBuffer<Writable> {
@@ -157,7 +247,7 @@ impl IoBuffer<Writable> {
             loop {
                 match self.get_result()? {
                     Some(size) => {
-                        trace!("Completed write operation (transferred {} bytes)", size);
+                        trace!("[{}] Completed write operation (transferred {} bytes)", self.label, size);
                         self.pending = false;
                         return Ok(());
                     },
@@ -169,8 +259,8 @@ impl IoBuffer<Writable> {
     }
 
     fn start_write(&mut self) -> HidResult<()> {
+        trace!("[{}] Starting new write operation", self.label);
         self.start_io(|device, buffer, overlapped| unsafe {
-            trace!("Starting new write operation");
             WriteFile(
                 device.handle(),
                 Some(buffer),
@@ -181,12 +271,12 @@ impl IoBuffer<Writable> {
     }
 
     pub async fn write(&mut self, data: &[u8]) -> HidResult<()> {
-        self.wait_for_write_to_complete().await.unwrap_or_else(|err| error!("Abandoned write failed: {err}"));
+        self.wait_for_write_to_complete().await.unwrap_or_else(|err| error!("[{}] Abandoned write failed: {err}", self.label));
 
-        trace!("Filling write buffer with data");
+        trace!("[{}] Filling write buffer with data", self.label);
         let mut data_size = data.len();
         if data_size > self.buffer.len() {
-            debug!("Data size ({}) exceeds maximum buffer size ({}), truncating data", data_size, self.buffer.len());
+            debug!("[{}] Data size ({}) exceeds maximum buffer size ({}), truncating data", self.label, data_size, self.buffer.len());
             data_size = self.buffer.len();
         }
         self.buffer[data_size..].fill(0);
@@ -197,6 +287,27 @@ impl IoBuffer<Writable> {
         Ok(())
     }
 
+    /// Like [Self::write], but cancels the write operation if it doesn't complete within `timeout`
+    ///
+    /// Returns `false` if the write timed out.
+    pub async fn write_timeout(&mut self, data: &[u8], timeout: Duration) -> HidResult<bool> {
+        let write = async { Some(self.write(data).await) };
+        let timeout = async {
+            Delay::new(timeout).await;
+            None
+        };
+        match write.or(timeout).await {
+            Some(result) => result.map(|_| true),
+            None => {
+                if self.pending {
+                    trace!("[{}] Write operation timed out", self.label);
+                    self.cancel_io_async().await?;
+                }
+                Ok(false)
+            }
+        }
+    }
+
 }
 
 #[derive(Default)]
@@ -232,4 +343,113 @@ impl Drop for Overlapped {
     fn drop(&mut self) {
         unsafe { CloseHandle(self.0.hEvent).unwrap_or_else(|err| warn!("Failed to close handle: {err}")) };
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use windows::core::{HSTRING, PCWSTR};
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::Storage::FileSystem::PIPE_ACCESS_DUPLEX;
+    use windows::Win32::System::Pipes::{CreateNamedPipeW, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_WAIT};
+
+    use super::*;
+    use crate::AccessMode;
+
+    /// The server end of a named pipe, which feeds data into the client end that is opened like a device
+    struct Server(HANDLE);
+
+    impl Server {
+        fn write(&self, data: &[u8]) {
+            unsafe { WriteFile(self.0, Some(data), None, None) }.unwrap();
+        }
+    }
+
+    impl Drop for Server {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) }.unwrap();
+        }
+    }
+
+    fn pipe(name: &str) -> (Server, Arc<Device>) {
+        let path = HSTRING::from(format!(r"\\.\pipe\async-hid-{}-{name}", std::process::id()));
+        let server = unsafe { CreateNamedPipeW(&path, PIPE_ACCESS_DUPLEX, PIPE_TYPE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS, 1, 4096, 4096, 0, None) };
+        assert!(!server.is_invalid(), "Failed to create the pipe");
+        let client = Device::open(PCWSTR(path.as_ptr()), Some(AccessMode::ReadWrite), false).unwrap();
+        (Server(server), Arc::new(client))
+    }
+
+    fn read_buffer(client: &Arc<Device>) -> IoBuffer<Readable> {
+        IoBuffer::new(client.clone(), "test".into(), 1).unwrap()
+    }
+
+    #[test]
+    fn interleaved_reads_and_cancels() {
+        let (server, client) = pipe("interleaved");
+        for i in 0..200u8 {
+            let mut buffer = read_buffer(&client);
+            buffer.start_read().unwrap();
+            assert!(buffer.pending);
+            if i % 2 == 0 {
+                // The read completes right away, so the cancellation finds nothing to cancel
+                server.write(&[i]);
+            }
+            buffer.cancel_io().unwrap();
+            buffer.close().unwrap();
+        }
+
+        // Neither the canceled nor the completed reads leave anything behind that could swallow new data
+        let mut buffer = read_buffer(&client);
+        let mut data = [0u8; 1];
+        server.write(&[0xAB]);
+        let outcome = pollster::block_on(buffer.read(&mut data, false)).unwrap();
+        assert_eq!((outcome.copied, data[0]), (1, 0xAB));
+    }
+
+    #[test]
+    fn abandoned_read_resumes() {
+        let (server, client) = pipe("abandoned");
+        let mut buffer = read_buffer(&client);
+        let mut data = [0u8; 1];
+        let timeout = async {
+            Delay::new(Duration::from_millis(10)).await;
+            None
+        };
+        let result = pollster::block_on(async { Some(buffer.read(&mut data, false).await) }.or(timeout));
+        assert!(result.is_none());
+        assert!(buffer.pending);
+
+        // The next read picks up the operation that is still pending instead of starting a new one
+        server.write(&[0x42]);
+        let outcome = pollster::block_on(buffer.read(&mut data, false)).unwrap();
+        assert_eq!((outcome.copied, data[0]), (1, 0x42));
+        assert!(!buffer.pending);
+    }
+
+    #[test]
+    fn write_timeout_cancels() {
+        let (_server, client) = pipe("write");
+        // Nobody reads from the pipe, so a write that exceeds its buffer never completes
+        let mut buffer = IoBuffer::<Writable>::new(client.clone(), "test".into(), 65536).unwrap();
+        for _ in 0..10 {
+            let completed = pollster::block_on(buffer.write_timeout(&[0x01; 16], Duration::from_millis(10))).unwrap();
+            assert!(!completed);
+            assert!(!buffer.pending);
+        }
+        buffer.close().unwrap();
+    }
+
+    #[test]
+    fn drop_cancels_pending_read() {
+        let (server, client) = pipe("drop");
+        for _ in 0..50 {
+            let mut buffer = read_buffer(&client);
+            buffer.start_read().unwrap();
+            drop(buffer);
+        }
+        let mut buffer = read_buffer(&client);
+        let mut data = [0u8; 1];
+        server.write(&[0x17]);
+        let outcome = pollster::block_on(buffer.read(&mut data, false)).unwrap();
+        assert_eq!((outcome.copied, data[0]), (1, 0x17));
+    }
+}
@@ -1,9 +1,11 @@
 use std::ffi::c_void;
+use std::mem::ManuallyDrop;
 use windows::core::PCWSTR;
-use windows::Win32::Devices::HumanInterfaceDevice::{HidD_FreePreparsedData, HidD_GetAttributes, HidD_GetPreparsedData, HidD_GetProductString, HidD_GetSerialNumberString, HidP_GetCaps, HIDD_ATTRIBUTES, HIDP_CAPS, PHIDP_PREPARSED_DATA};
-use windows::Win32::Foundation::{CloseHandle, BOOLEAN, HANDLE};
+use windows::Win32::Devices::HumanInterfaceDevice::{HidD_FreePreparsedData, HidD_GetAttributes, HidD_GetFeature, HidD_GetIndexedString, HidD_GetInputReport, HidD_GetManufacturerString, HidD_GetPreparsedData, HidD_GetProductString, HidD_GetSerialNumberString, HidD_SetFeature, HidP_Feature, HidP_GetButtonCaps, HidP_GetCaps, HidP_GetValueCaps, HidP_Input, HidP_Output, HIDD_ATTRIBUTES, HIDP_BUTTON_CAPS, HIDP_CAPS, HIDP_VALUE_CAPS, PHIDP_PREPARSED_DATA};
+use windows::Win32::Foundation::{CloseHandle, BOOLEAN, E_ACCESSDENIED, HANDLE};
 use windows::Win32::Storage::FileSystem::{CreateFileW, FILE_FLAG_OVERLAPPED, FILE_SHARE_NONE, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING};
-use crate::{AccessMode, HidResult};
+use crate::backend::hidp;
+use crate::{AccessMode, ErrorSource, HidError, HidResult, ReportType, Usage};
 
 #[derive(Debug, Eq, PartialEq)]
 #[repr(transparent)]
@@ -11,7 +13,7 @@ pub struct Device(HANDLE);
 
 impl Device {
 
-    pub fn open(path: PCWSTR, access_mode: Option<AccessMode>) -> HidResult<Device> {
+    pub fn open(path: PCWSTR, access_mode: Option<AccessMode>, exclusive: bool) -> HidResult<Device> {
         let handle = unsafe {
             CreateFileW(
                 path,
@@ -21,16 +23,29 @@ impl Device {
                     Some(AccessMode::ReadWrite) => FILE_SHARE_READ | FILE_SHARE_WRITE,
                     None => FILE_SHARE_NONE,
                 }.0,
-                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                match exclusive {
+                    true => FILE_SHARE_NONE,
+                    false => FILE_SHARE_READ | FILE_SHARE_WRITE
+                },
                 None,
                 OPEN_EXISTING,
                 FILE_FLAG_OVERLAPPED,
                 HANDLE::default()
             )
-        }?;
+        }
+        .map_err(|err| match err.code() == E_ACCESSDENIED {
+            true => HidError::from(ErrorSource::PermissionDenied(err)),
+            false => HidError::from(err)
+        })?;
         Ok(Device(handle))
     }
 
+    pub fn close(self) -> HidResult<()> {
+        let handle = ManuallyDrop::new(self).0;
+        unsafe { CloseHandle(handle)? };
+        Ok(())
+    }
+
     pub fn handle(&self) -> HANDLE {
         self.0
     }
@@ -48,8 +63,12 @@ impl Device {
     }
 
     fn read_string(&self, func: unsafe fn(HANDLE, *mut c_void, u32) -> BOOLEAN) -> HidResult<String> {
+        self.read_string_with(|handle, buffer, len| unsafe { func(handle, buffer, len) })
+    }
+
+    fn read_string_with(&self, func: impl FnOnce(HANDLE, *mut c_void, u32) -> BOOLEAN) -> HidResult<String> {
         let mut buffer = [0u16; 256];
-        unsafe { func(self.0, buffer.as_mut_ptr() as _, (size_of::<u16>() * buffer.len()) as u32) }.ok()?;
+        func(self.0, buffer.as_mut_ptr() as _, (size_of::<u16>() * buffer.len()) as u32).ok()?;
         let serial_number = buffer
             .split(|c| *c == 0x0)
             .map(String::from_utf16_lossy)
@@ -66,6 +85,35 @@ impl Device {
         self.read_string(HidD_GetProductString)
     }
 
+    pub fn manufacturer(&self) -> HidResult<String> {
+        self.read_string(HidD_GetManufacturerString)
+    }
+
+    pub fn indexed_string(&self, index: u32) -> HidResult<String> {
+        self.read_string_with(|handle, buffer, len| unsafe { HidD_GetIndexedString(handle, index, buffer, len) })
+    }
+
+    pub fn get_feature(&self, buf: &mut [u8]) -> HidResult<()> {
+        unsafe {
+            HidD_GetFeature(self.0, buf.as_mut_ptr() as _, buf.len() as u32).ok()?;
+        }
+        Ok(())
+    }
+
+    pub fn set_feature(&self, buf: &[u8]) -> HidResult<()> {
+        unsafe {
+            HidD_SetFeature(self.0, buf.as_ptr() as _, buf.len() as u32).ok()?;
+        }
+        Ok(())
+    }
+
+    pub fn get_input_report(&self, buf: &mut [u8]) -> HidResult<()> {
+        unsafe {
+            HidD_GetInputReport(self.0, buf.as_mut_ptr() as _, buf.len() as u32).ok()?;
+        }
+        Ok(())
+    }
+
 }
 
 impl Drop for Device {
@@ -96,6 +144,42 @@ impl PreparsedData {
         Ok(caps)
     }
 
+    pub fn decode(&self, report_type: ReportType, numbered: bool, report: &[u8]) -> HidResult<Vec<(Usage, i64)>> {
+        hidp::decode_report(self.0, report_type, numbered, report)
+    }
+
+    /// Collects the distinct report ids of all button and value caps of the given report type
+    pub fn report_ids(&self, report_type: ReportType) -> HidResult<Vec<u8>> {
+        let caps = self.caps()?;
+        let (hid_report_type, button_caps_len, value_caps_len) = match report_type {
+            ReportType::Input => (HidP_Input, caps.NumberInputButtonCaps, caps.NumberInputValueCaps),
+            ReportType::Output => (HidP_Output, caps.NumberOutputButtonCaps, caps.NumberOutputValueCaps),
+            ReportType::Feature => (HidP_Feature, caps.NumberFeatureButtonCaps, caps.NumberFeatureValueCaps)
+        };
+
+        let mut button_caps = vec![HIDP_BUTTON_CAPS::default(); button_caps_len as usize];
+        let mut value_caps = vec![HIDP_VALUE_CAPS::default(); value_caps_len as usize];
+        let mut button_caps_len = button_caps_len;
+        let mut value_caps_len = value_caps_len;
+        unsafe {
+            if button_caps_len > 0 {
+                HidP_GetButtonCaps(hid_report_type, button_caps.as_mut_ptr(), &mut button_caps_len, self.0).ok()?;
+            }
+            if value_caps_len > 0 {
+                HidP_GetValueCaps(hid_report_type, value_caps.as_mut_ptr(), &mut value_caps_len, self.0).ok()?;
+            }
+        }
+
+        let mut ids = button_caps[..button_caps_len as usize]
+            .iter()
+            .map(|c| c.ReportID)
+            .chain(value_caps[..value_caps_len as usize].iter().map(|c| c.ReportID))
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+        Ok(ids)
+    }
+
 }
 
 impl Drop for PreparsedData {
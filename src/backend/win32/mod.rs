@@ -6,93 +6,367 @@ mod string;
 mod interface;
 mod mutex;
 
-use std::sync::{Arc};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
 
 use futures_lite::Stream;
 use futures_lite::stream::iter;
-use windows::core::{HRESULT};
-use windows::Win32::Devices::DeviceAndDriverInstallation::{CM_MapCrToWin32Err, CONFIGRET};
 use windows::Win32::Devices::HumanInterfaceDevice::HidD_SetNumInputBuffers;
-use windows::Win32::Foundation::E_FAIL;
+use windows::Win32::Foundation::ERROR_SHARING_VIOLATION;
+use crate::backend::{cfgmgr, interface_label};
 use crate::error::{ErrorSource, HidResult};
-use crate::{ensure, AccessMode, DeviceId, DeviceInfo, HidError, SerialNumberExt};
+use crate::filter::any_matches_ids;
+use crate::{ensure, AccessMode, AccessState, BackendType, BusType, DeviceFilter, DeviceId, DeviceInfo, EnumerateOptions, EnumerationError, HidError, ReadConfig, ReadOutcome, ReportType, SerialNumberExt, Usage};
 use crate::backend::win32::buffer::{IoBuffer, Readable, Writable};
 use crate::backend::win32::device::Device;
 use interface::Interface;
 use crate::backend::win32::mutex::SimpleMutex;
 use crate::backend::win32::string::{U16Str, U16String};
 
-pub async fn enumerate() -> HidResult<impl Stream<Item = DeviceInfo> + Unpin + Send> {
-    let devices = Interface::get_interface_list()?
-        .iter()
-        .filter_map(|i| {
-            get_device_information(i)
-                .map_err(|e| log::trace!("Failed to query device information for {i:?}\n\tbecause {e}"))
-                .ok()
-        })
-        .collect::<Vec<_>>();
+/// The devices found by previous enumerations together with the options that were used to query them
+static DEVICE_CACHE: Mutex<BTreeMap<U16String, (EnumerateOptions, DeviceInfo)>> = Mutex::new(BTreeMap::new());
+
+pub const BACKEND_TYPE: BackendType = BackendType::Win32;
+
+pub async fn enumerate(
+    options: EnumerateOptions, filters: &[DeviceFilter]
+) -> HidResult<impl Stream<Item = Result<DeviceInfo, EnumerationError>> + Unpin + Send> {
+    let interfaces = Interface::get_interface_list()?;
+    let mut cache = match options.use_cache {
+        true => Some(DEVICE_CACHE.lock().unwrap_or_else(PoisonError::into_inner)),
+        false => None
+    };
+    let mut devices = Vec::new();
+    for interface in interfaces.iter() {
+        let info = match cache.as_ref().and_then(|cache| cache.get(interface)) {
+            Some((cached_options, info)) if *cached_options == options => Some(info.clone()),
+            _ => match get_device_information(interface, options, filters) {
+                Ok(Some(info)) => {
+                    if let Some(cache) = &mut cache {
+                        cache.insert(interface.to_owned(), (options, info.clone()));
+                    }
+                    Some(info)
+                }
+                Ok(None) => None,
+                Err(error) => {
+                    let device = String::from_utf16_lossy(interface.as_slice());
+                    devices.push(Err(EnumerationError { device, error }));
+                    None
+                }
+            }
+        };
+        devices.extend(
+            info.filter(|info| any_matches_ids(filters, info.vendor_id, info.product_id))
+                .map(Ok)
+        );
+    }
+    if let Some(cache) = &mut cache {
+        // Devices that were unplugged in the meantime get queried again once they reappear
+        cache.retain(|id, _| interfaces.iter().any(|interface| interface == &**id));
+    }
     Ok(iter(devices))
 }
 
+pub fn clear_enumeration_cache() {
+    DEVICE_CACHE.lock().unwrap_or_else(PoisonError::into_inner).clear();
+}
+
 impl SerialNumberExt for DeviceInfo {
     fn serial_number(&self) -> Option<&str> {
-        self.private_data
-            .serial_number
-            .as_ref()
-            .map(String::as_str)
+        self.private_data.serial_number.as_deref()
     }
 }
 
-fn get_device_information(device: &U16Str) -> HidResult<DeviceInfo> {
+impl DeviceInfo {
+    pub(crate) fn physical_device_id(&self) -> Option<&str> {
+        self.private_data.container_id.as_deref()
+    }
+}
+
+pub fn parse_device_id(id: &str) -> HidResult<BackendDeviceId> {
+    ensure!(
+        id.starts_with(r"\\?\") && !id.contains('\0'),
+        HidError::custom(format!("{id} is not a valid device interface path"))
+    );
+    Ok(U16String::from(id))
+}
+
+pub fn format_device_id(id: &BackendDeviceId) -> String {
+    String::from_utf16_lossy(id.as_slice())
+}
+
+pub async fn get_device_info(id: &BackendDeviceId) -> HidResult<DeviceInfo> {
+    get_device_information(id, EnumerateOptions::default(), &[])?.ok_or(HidError::custom("Device was ruled out without any filters"))
+}
+
+/// Queries the information of the given device interface or returns `None` if the vendor and product id are ruled out by `filters`
+fn get_device_information(device: &U16Str, options: EnumerateOptions, filters: &[DeviceFilter]) -> HidResult<Option<DeviceInfo>> {
     let id = device.to_owned();
-    let device = Device::open(device.as_ptr(), None)?;
-    let name = device.name()?;
+    let device = Device::open(device.as_ptr(), None, false)?;
     let attribs = device.attributes()?;
-    let caps = device.preparsed_data()?.caps()?;
-    let serial_number = device.serial_number().ok();
-    Ok(DeviceInfo {
+    if !any_matches_ids(filters, attribs.VendorID, attribs.ProductID) {
+        return Ok(None);
+    }
+    let (name, serial_number, manufacturer) = match options.fetch_strings {
+        true => (
+            device.name()?,
+            device.serial_number().ok(),
+            device.manufacturer().ok().filter(|s| !s.is_empty())
+        ),
+        false => (String::new(), None, None)
+    };
+    // Fall back to the name shown in the device manager for devices without a product string
+    let name = match options.fetch_strings && name.trim().is_empty() {
+        true => cfgmgr::get_friendly_name(id.as_ptr())
+            .map_err(|err| log::trace!("Failed to query the friendly name of {id:?}\n\tbecause {err}"))
+            .unwrap_or(name),
+        false => name
+    };
+    let (usage_id, usage_page) = match options.fetch_caps {
+        true => {
+            let caps = device.preparsed_data()?.caps()?;
+            (caps.Usage, caps.UsagePage)
+        }
+        false => (0, 0)
+    };
+    let container_id = cfgmgr::get_container_id(id.as_ptr())
+        .map_err(|err| log::trace!("Failed to query the container id of {id:?}\n\tbecause {err}"))
+        .ok()
+        .map(|guid| format!("{guid:?}"));
+    let bus_type = cfgmgr::get_bus_type(id.as_ptr()).unwrap_or_else(|err| {
+        log::trace!("Failed to query the bus type of {id:?}\n\tbecause {err}");
+        BusType::Unknown
+    });
+    Ok(Some(DeviceInfo {
         id: DeviceId::from(id),
         name,
+        manufacturer,
         product_id: attribs.ProductID,
         vendor_id: attribs.VendorID,
-        usage_id: caps.Usage,
-        usage_page: caps.UsagePage,
+        usage_id,
+        usage_page,
+        polling_interval: None,
+        bus_type,
+        release_number: attribs.VersionNumber,
         private_data: BackendPrivateData {
-            serial_number
+            serial_number,
+            container_id
         }
-    })
+    }))
 }
 
 
 #[derive(Debug)]
 pub struct BackendDevice {
+    id: BackendDeviceId,
+    device: Arc<Device>,
+    label: Arc<str>,
+    input_report_len: usize,
+    output_report_len: usize,
+    feature_report_len: usize,
+    numbered_input_reports: bool,
     read_buffer: SimpleMutex<IoBuffer<Readable>>,
     write_buffer: SimpleMutex<IoBuffer<Writable>>,
 }
 
-pub async fn open(id: &BackendDeviceId, mode: AccessMode) -> HidResult<BackendDevice> {
-    let device = Arc::new(Device::open(id.as_ptr(), Some(mode))?);
+pub fn is_connected(id: &BackendDeviceId) -> bool {
+    // Opening the device without requesting any access doesn't interfere with the existing handles
+    Device::open(id.as_ptr(), None, false).is_ok()
+}
+
+pub fn access_state(id: &BackendDeviceId) -> HidResult<AccessState> {
+    match Device::open(id.as_ptr(), Some(AccessMode::ReadWrite), false) {
+        Ok(_) => Ok(AccessState::Available),
+        Err(err) if err.is_permission_denied() => Ok(AccessState::PermissionDenied),
+        Err(err) if err.raw_os_error() == Some(ERROR_SHARING_VIOLATION.to_hresult().0) => Ok(AccessState::BusyExclusive),
+        Err(err) => Err(err)
+    }
+}
+
+pub fn location(id: &BackendDeviceId) -> Option<String> {
+    cfgmgr::get_location(id.as_ptr())
+        .map_err(|err| log::trace!("Failed to query the location of {id:?}\n\tbecause {err}"))
+        .ok()
+}
+
+pub async fn open(id: &BackendDeviceId, mode: AccessMode, config: &ReadConfig) -> HidResult<BackendDevice> {
+    let device = Arc::new(Device::open(id.as_ptr(), Some(mode), config.exclusive)?);
 
     unsafe {
-        HidD_SetNumInputBuffers(device.handle(), 64).ok()?;
+        // Windows requires at least two input buffers and rejects more than 512
+        HidD_SetNumInputBuffers(device.handle(), config.queue_limit().clamp(2, 512) as u32).ok()?;
     }
-    let caps = device.preparsed_data()?.caps()?;
+    let preparsed_data = device.preparsed_data()?;
+    let caps = preparsed_data.caps()?;
+    let numbered_input_reports = config.numbered_reports(|| {
+        preparsed_data
+            .report_ids(ReportType::Input)
+            .is_ok_and(|ids| ids.iter().any(|id| *id != 0))
+    });
 
-    let read_buffer = SimpleMutex::new(IoBuffer::<Readable>::new(device.clone(), caps.InputReportByteLength as usize)?);
-    let write_buffer = SimpleMutex::new(IoBuffer::<Writable>::new(device, caps.OutputReportByteLength as usize)?);
+    let label: Arc<str> = interface_label(&String::from_utf16_lossy(id.as_slice())).into();
+    let read_buffer = SimpleMutex::new(IoBuffer::<Readable>::new(device.clone(), label.clone(), caps.InputReportByteLength as usize)?);
+    let write_buffer = SimpleMutex::new(IoBuffer::<Writable>::new(device.clone(), label.clone(), caps.OutputReportByteLength as usize)?);
     Ok(BackendDevice {
+        id: id.clone(),
+        device,
+        label,
+        input_report_len: caps.InputReportByteLength as usize,
+        output_report_len: caps.OutputReportByteLength as usize,
+        feature_report_len: caps.FeatureReportByteLength as usize,
+        numbered_input_reports,
         read_buffer,
         write_buffer,
     })
 }
 
 impl BackendDevice {
-    pub async fn read_input_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+    pub fn is_connected(&self) -> bool {
+        is_connected(&self.id)
+    }
+
+    pub async fn read_input_report(&self, buf: &mut [u8]) -> HidResult<ReadOutcome> {
         match self.read_buffer.try_lock() {
-            Some(mut buffer) => {
-                let len = buffer.read(buf).await?;
-                Ok(len)
-            },
+            Some(mut buffer) => buffer.read(buf, !self.numbered_input_reports).await,
+            None => Err(HidError::custom("Another read operation is in progress"))
+        }
+    }
+
+    pub async fn read_input_report_raw(&self, buf: &mut [u8]) -> HidResult<usize> {
+        match self.read_buffer.try_lock() {
+            Some(mut buffer) => Ok(buffer.read(buf, false).await?.copied),
+            None => Err(HidError::custom("Another read operation is in progress"))
+        }
+    }
+
+    pub async fn get_input_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        ensure!(!buf.is_empty(), HidError::zero_sized_data());
+        ensure!(self.input_report_len > 0, HidError::custom("Device has no input reports"));
+        let mut report = vec![0u8; self.input_report_len];
+        report[0] = buf[0];
+        self.device.get_input_report(&mut report)?;
+        let mut copy_len = report.len();
+        if copy_len > buf.len() {
+            log::debug!("[{}] Input report ({}) is larger than the provided buffer ({}), truncating data", self.label, copy_len, buf.len());
+            copy_len = buf.len();
+        }
+        buf[..copy_len].copy_from_slice(&report[..copy_len]);
+        Ok(copy_len)
+    }
+
+    pub fn input_report_len(&self, _report_id: u8) -> HidResult<usize> {
+        Ok(self.input_report_len)
+    }
+
+    pub fn max_input_report_len(&self) -> HidResult<usize> {
+        Ok(self.input_report_len)
+    }
+
+    pub fn decode_input_report(&self, report: &[u8]) -> HidResult<Vec<(Usage, i64)>> {
+        self.device
+            .preparsed_data()?
+            .decode(ReportType::Input, self.numbered_input_reports, report)
+    }
+
+    pub fn serial_number(&self) -> HidResult<Option<String>> {
+        // Devices without a serial number string fail the request instead of returning an empty string
+        Ok(self
+            .device
+            .serial_number()
+            .map_err(|err| log::trace!("Failed to read the serial number\n\tbecause {err}"))
+            .ok()
+            .filter(|s| !s.is_empty()))
+    }
+
+    pub async fn control_transfer(&self, _request_type: u8, _request: u8, _value: u16, _index: u16, _data: &mut [u8]) -> HidResult<usize> {
+        Err(HidError::custom("Control transfers are not supported on this platform"))
+    }
+
+    pub async fn get_output_report(&self, _buf: &mut [u8]) -> HidResult<usize> {
+        Err(HidError::custom("Requesting output reports is not supported on this platform"))
+    }
+
+    pub async fn indexed_string(&self, index: u32) -> HidResult<Option<String>> {
+        Ok(Some(self.device.indexed_string(index)?).filter(|s| !s.is_empty()))
+    }
+
+    pub async fn read_feature_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        ensure!(!buf.is_empty(), HidError::zero_sized_data());
+        ensure!(self.feature_report_len > 0, HidError::custom("Device has no feature reports"));
+        let mut report = vec![0u8; self.feature_report_len];
+        report[0] = buf[0];
+        self.device.get_feature(&mut report)?;
+        let mut copy_len = report.len();
+        if copy_len > buf.len() {
+            log::debug!("[{}] Feature report ({}) is larger than the provided buffer ({}), truncating data", self.label, copy_len, buf.len());
+            copy_len = buf.len();
+        }
+        buf[..copy_len].copy_from_slice(&report[..copy_len]);
+        Ok(copy_len)
+    }
+
+    pub async fn read_feature_report_timeout(&self, buf: &mut [u8], _timeout: Duration) -> HidResult<Option<usize>> {
+        // HidD_GetFeature blocks, but the HID class driver gives up on unresponsive devices on its own
+        self.read_feature_report(buf).await.map(Some)
+    }
+
+    pub async fn write_feature_report(&self, buf: &[u8]) -> HidResult<()> {
+        ensure!(!buf.is_empty(), HidError::zero_sized_data());
+        ensure!(self.feature_report_len > 0, HidError::custom("Device has no feature reports"));
+        ensure!(buf.len() <= self.feature_report_len, HidError::custom("Feature report is too large"));
+        // Windows rejects feature reports that are not padded to the size of the largest one
+        let mut report = vec![0u8; self.feature_report_len];
+        report[..buf.len()].copy_from_slice(buf);
+        self.device.set_feature(&report)
+    }
+
+    pub fn feature_report_len(&self, _report_id: u8) -> HidResult<usize> {
+        Ok(self.feature_report_len)
+    }
+
+    pub fn report_descriptor(&self) -> HidResult<Vec<u8>> {
+        // Windows only exposes the preparsed data, which can't be turned back into the original descriptor
+        Err(HidError::custom("Reading the report descriptor is not supported on this platform"))
+    }
+
+    pub fn feature_report_ids(&self) -> HidResult<Vec<(u8, usize)>> {
+        // Windows always pads feature reports to the size of the largest one
+        let ids = self.device.preparsed_data()?.report_ids(ReportType::Feature)?;
+        Ok(ids.into_iter().map(|id| (id, self.feature_report_len)).collect())
+    }
+
+    pub fn input_report_ids(&self) -> HidResult<Vec<(u8, usize)>> {
+        // Windows always pads input reports to the size of the largest one
+        let ids = self.device.preparsed_data()?.report_ids(ReportType::Input)?;
+        Ok(ids.into_iter().map(|id| (id, self.input_report_len)).collect())
+    }
+
+    pub fn output_report_ids(&self) -> HidResult<Vec<(u8, usize)>> {
+        // Windows always pads output reports to the size of the largest one
+        let ids = self.device.preparsed_data()?.report_ids(ReportType::Output)?;
+        Ok(ids.into_iter().map(|id| (id, self.output_report_len)).collect())
+    }
+
+    pub fn usages(&self) -> HidResult<Vec<Usage>> {
+        // Every top-level collection is a separate device on Windows
+        let caps = self.device.preparsed_data()?.caps()?;
+        Ok(vec![Usage::new(caps.UsagePage, caps.Usage)])
+    }
+
+    pub fn collection_input_report_ids(&self, _usage_page: u16, _usage_id: u16) -> HidResult<Option<Vec<u8>>> {
+        // Every top-level collection is a separate device on Windows
+        Ok(None)
+    }
+
+    pub fn dropped_reports(&self) -> u64 {
+        // The HID class driver silently drops the oldest report once its buffers are full
+        0
+    }
+
+    pub fn clear_input_reports(&self) -> HidResult<()> {
+        match self.read_buffer.try_lock() {
+            Some(mut buffer) => buffer.clear(),
             None => Err(HidError::custom("Another read operation is in progress"))
         }
     }
@@ -107,29 +381,54 @@ impl BackendDevice {
             None => Err(HidError::custom("Another write operation is in progress"))
         }
     }
+
+    pub fn close(self) -> HidResult<()> {
+        let read_result = self.read_buffer.into_inner().close();
+        let write_result = self.write_buffer.into_inner().close();
+        // The buffers are gone, so this is the last reference to the device
+        let device_result = Arc::into_inner(self.device).map_or(Ok(()), Device::close);
+        read_result.and(write_result).and(device_result)
+    }
+
+    pub async fn write_output_reports(&self, reports: &[&[u8]]) -> HidResult<()> {
+        ensure!(reports.iter().all(|report| !report.is_empty()), HidError::zero_sized_data());
+        // The buffer is locked once for the whole batch, so no other write can end up in between
+        match self.write_buffer.try_lock() {
+            Some(mut buffer) => {
+                for report in reports {
+                    buffer.write(report).await?;
+                }
+                Ok(())
+            },
+            None => Err(HidError::custom("Another write operation is in progress"))
+        }
+    }
+
+    pub async fn write_output_report_timeout(&self, buf: &[u8], timeout: Duration) -> HidResult<bool> {
+        ensure!(!buf.is_empty(), HidError::zero_sized_data());
+        match self.write_buffer.try_lock() {
+            Some(mut buffer) => buffer.write_timeout(buf, timeout).await,
+            None => Err(HidError::custom("Another write operation is in progress"))
+        }
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BackendPrivateData {
-    serial_number: Option<String>
+    serial_number: Option<String>,
+    container_id: Option<String>
 }
 
 pub type BackendDeviceId = U16String;
 pub type BackendError = windows::core::Error;
 
+pub fn raw_os_error(err: &BackendError) -> Option<i32> {
+    Some(err.code().0)
+}
+
 impl From<BackendError> for ErrorSource {
     fn from(value: BackendError) -> Self {
         ErrorSource::PlatformSpecific(value)
     }
 }
-
-impl From<CONFIGRET> for ErrorSource {
-    fn from(value: CONFIGRET) -> Self {
-        const UNKNOWN_ERROR: u32 = 0xFFFF;
-        let hresult = match unsafe { CM_MapCrToWin32Err(value, UNKNOWN_ERROR) } {
-            UNKNOWN_ERROR => E_FAIL,
-            win32 => HRESULT::from_win32(win32),
-        };
-        ErrorSource::PlatformSpecific(windows::core::Error::from(hresult))
-    }
-}
\ No newline at end of file
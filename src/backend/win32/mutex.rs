@@ -26,6 +26,10 @@ impl<T> SimpleMutex<T> {
     pub const fn new(t: T) -> SimpleMutex<T> {
         SimpleMutex { lock: Lock::new(), data: UnsafeCell::new(t) }
     }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
 }
 
 impl<T: ?Sized> SimpleMutex<T> {
@@ -111,6 +111,12 @@ impl ToOwned for U16Str {
 #[derive(Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub struct U16String(Vec<u16>);
 
+impl From<&str> for U16String {
+    fn from(value: &str) -> Self {
+        U16String(value.encode_utf16().chain([0]).collect())
+    }
+}
+
 impl Debug for U16String {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self.deref())
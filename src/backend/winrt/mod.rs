@@ -3,28 +3,38 @@ mod win32;
 
 use std::fmt::Display;
 use std::hash::{Hash, Hasher};
+use std::num::NonZeroU8;
 use std::ops::{Deref, DerefMut};
 use std::pin::Pin;
-use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::task::{Context, Poll};
+use std::time::Duration;
 
+use flume::r#async::RecvStream;
 use flume::{Receiver, TrySendError};
-use futures_lite::{Stream, StreamExt};
+use futures_lite::{ready, FutureExt, Stream, StreamExt};
+use futures_timer::Delay;
 use windows::core::{h, HSTRING};
-use windows::Devices::Enumeration::{DeviceInformation, DeviceInformationCollection};
-use windows::Devices::HumanInterfaceDevice::{HidDevice, HidInputReport, HidInputReportReceivedEventArgs};
+use windows::Devices::Enumeration::{DeviceInformation, DeviceWatcher, DeviceWatcherStatus};
+use windows::Devices::HumanInterfaceDevice::{HidDevice, HidInputReport, HidInputReportReceivedEventArgs, HidOutputReport};
 use windows::Foundation::{EventRegistrationToken, TypedEventHandler};
 use windows::Storage::FileAccessMode;
 
 use crate::backend::winrt::utils::{IBufferExt, WinResultExt};
+use crate::backend::interface_label;
 use crate::error::{ErrorSource, HidResult};
-use crate::{ensure, AccessMode, DeviceInfo, HidError};
+use crate::{ensure, AccessMode, AccessState, BackendType, DeviceFilter, DeviceInfo, EnumerateOptions, EnumerationError, HidError, OverflowPolicy, ReadConfig, ReadOutcome, ReportType, Usage};
 
 const DEVICE_SELECTOR: &HSTRING = h!(
     r#"System.Devices.InterfaceClassGuid:="{4D1E55B2-F16F-11CF-88CB-001111000030}" AND System.Devices.InterfaceEnabled:=System.StructuredQueryType.Boolean#True"#
 );
 
-pub async fn enumerate() -> HidResult<impl Stream<Item = DeviceInfo> + Unpin + Send> {
+pub const BACKEND_TYPE: BackendType = BackendType::WinRt;
+
+pub async fn enumerate(
+    _options: EnumerateOptions, filters: &[DeviceFilter]
+) -> HidResult<impl Stream<Item = Result<DeviceInfo, EnumerationError>> + Unpin + Send> {
     //let devices = DeviceInformation::FindAllAsyncAqsFilter(DEVICE_SELECTOR)?
     //    .await?
     //    .into_iter()
@@ -33,13 +43,10 @@ pub async fn enumerate() -> HidResult<impl Stream<Item = DeviceInfo> + Unpin + S
     //    .filter_map(|info| ready(info.ok()))
     //    .collect()
     //    .await;
-    let devices = DeviceInformation::FindAllAsyncAqsFilter(DEVICE_SELECTOR)?
-        .await?;
-    let devices = DeviceInformationSteam::from(devices)
-        .then(|info| Box::pin(get_device_information(info)))
-        .filter_map(|r| {
-            r.map_err(|e| log::trace!("Failed to query device information\n\tbecause {e:?}"))
-                .ok()
+    let devices = DeviceWatcherStream::new(&device_selector(filters))?
+        .then(|info| {
+            let device = info.Id().map(|id| id.to_string_lossy()).unwrap_or_default();
+            Box::pin(async move { get_device_information(info).await.map_err(|error| EnumerationError { device, error }) })
         });
     //.collect()
     //.await;
@@ -47,13 +54,66 @@ pub async fn enumerate() -> HidResult<impl Stream<Item = DeviceInfo> + Unpin + S
 }
 
 
+/// Extends the base selector with the criteria of the filters, so the watcher only reports matching devices
+fn device_selector(filters: &[DeviceFilter]) -> HSTRING {
+    let filters = filters
+        .iter()
+        .map(|filter| {
+            [
+                ("VendorId", filter.vendor_id),
+                ("ProductId", filter.product_id),
+                ("UsagePage", filter.usage_page),
+                ("UsageId", filter.usage_id)
+            ]
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|value| format!("System.DeviceInterface.Hid.{key}:={value}")))
+            .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    // A filter without any criteria matches every device, so there is nothing to add
+    if filters.is_empty() || filters.iter().any(Vec::is_empty) {
+        return DEVICE_SELECTOR.clone();
+    }
+    let filters = filters
+        .iter()
+        .map(|criteria| format!("({})", criteria.join(" AND ")))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    HSTRING::from(format!("{DEVICE_SELECTOR} AND ({filters})"))
+}
+
 //fn get_device_information_unpin(device: DeviceInformation) -> impl Future<Output = HidResult<DeviceInfo>> + Unpin {
 //
 //}
 
+pub fn clear_enumeration_cache() {
+    // Enumeration doesn't open any devices, so there is nothing to cache
+}
+
+pub fn parse_device_id(id: &str) -> HidResult<BackendDeviceId> {
+    ensure!(
+        id.starts_with(r"\\?\") && !id.contains('\0'),
+        HidError::custom(format!("{id} is not a valid device interface path"))
+    );
+    Ok(HashableHSTRING(HSTRING::from(id)))
+}
+
+pub fn format_device_id(id: &BackendDeviceId) -> String {
+    id.to_string()
+}
+
+pub async fn get_device_info(id: &BackendDeviceId) -> HidResult<DeviceInfo> {
+    let device = DeviceInformation::CreateFromIdAsync(id)?
+        .await
+        .on_null_result(|| HidError::custom(format!("Failed to find {}", id)))?;
+    get_device_information(device).await
+}
+
 async fn get_device_information(device: DeviceInformation) -> HidResult<DeviceInfo> {
     let id = device.Id()?;
     let name = device.Name()?.to_string_lossy();
+    let bus_type = win32::get_bus_type(&id);
+    let manufacturer = win32::get_manufacturer(&id);
     let device = HidDevice::FromIdAsync(&id, FileAccessMode::Read)?;
     let device = device
         .await
@@ -61,10 +121,14 @@ async fn get_device_information(device: DeviceInformation) -> HidResult<DeviceIn
     Ok(DeviceInfo {
         id: HashableHSTRING(id).into(),
         name,
+        manufacturer,
         product_id: device.ProductId()?,
         vendor_id: device.VendorId()?,
         usage_id: device.UsageId()?,
         usage_page: device.UsagePage()?,
+        polling_interval: None,
+        bus_type,
+        release_number: device.Version()?,
         private_data: BackendPrivateData::default()
     })
 }
@@ -72,25 +136,42 @@ async fn get_device_information(device: DeviceInformation) -> HidResult<DeviceIn
 #[derive(Debug, Clone)]
 struct InputReceiver {
     buffer: Receiver<HidInputReport>,
-    token: EventRegistrationToken
+    token: EventRegistrationToken,
+    dropped: Arc<AtomicU64>
 }
 
 impl InputReceiver {
-    fn new(device: &HidDevice) -> HidResult<Self> {
-        let (sender, receiver) = flume::bounded(64);
+    fn new(device: &HidDevice, label: String, config: &ReadConfig) -> HidResult<Self> {
+        let limit = config.queue_limit();
+        let (sender, receiver) = match config.overflow {
+            OverflowPolicy::DropOldest => flume::bounded(config.queue_capacity()),
+            OverflowPolicy::Grow(_) => flume::unbounded()
+        };
         let drain = receiver.clone();
+        let dropped = Arc::new(AtomicU64::new(0));
+        let counter = dropped.clone();
         let token = device.InputReportReceived(&TypedEventHandler::new(move |_, args: &Option<HidInputReportReceivedEventArgs>| {
             if let Some(args) = args {
                 let mut msg = args.Report()?;
+                if sender.len() >= limit && drain.try_recv().is_ok() {
+                    log::trace!("[{label}] Dropping previous input report because the queue is full");
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
                 while let Err(TrySendError::Full(ret)) = sender.try_send(msg) {
-                    log::trace!("Dropping previous input report because the queue is full");
-                    let _ = drain.try_recv();
+                    log::trace!("[{label}] Dropping previous input report because the queue is full");
+                    if drain.try_recv().is_ok() {
+                        counter.fetch_add(1, Ordering::Relaxed);
+                    }
                     msg = ret;
                 }
             }
             Ok(())
         }))?;
-        Ok(Self { buffer: receiver, token })
+        Ok(Self {
+            buffer: receiver,
+            token,
+            dropped
+        })
     }
 
     async fn recv_async(&self) -> HidInputReport {
@@ -100,6 +181,10 @@ impl InputReceiver {
             .expect("Input report handler got dropped unexpectedly")
     }
 
+    fn clear(&self) {
+        while self.buffer.try_recv().is_ok() {}
+    }
+
     fn stop(self, device: &HidDevice) -> HidResult<()> {
         Ok(device.RemoveInputReportReceived(self.token)?)
     }
@@ -107,8 +192,20 @@ impl InputReceiver {
 
 #[derive(Debug, Clone)]
 pub struct BackendDevice {
+    id: BackendDeviceId,
     device: HidDevice,
-    input: Option<InputReceiver>
+    input: Option<InputReceiver>,
+    numbered_input_reports: bool
+}
+
+impl BackendDevice {
+    pub fn close(mut self) -> HidResult<()> {
+        if let Some(input) = self.input.take() {
+            input.stop(&self.device)?;
+        }
+        self.device.Close()?;
+        Ok(())
+    }
 }
 
 impl Drop for BackendDevice {
@@ -121,19 +218,44 @@ impl Drop for BackendDevice {
     }
 }
 
-pub async fn open(id: &BackendDeviceId, mode: AccessMode) -> HidResult<BackendDevice> {
+pub fn is_connected(id: &BackendDeviceId) -> bool {
+    win32::is_connected(id)
+}
+
+pub fn access_state(id: &BackendDeviceId) -> HidResult<AccessState> {
+    win32::access_state(id)
+}
+
+pub fn location(id: &BackendDeviceId) -> Option<String> {
+    win32::get_location(id)
+}
+
+pub async fn open(id: &BackendDeviceId, mode: AccessMode, config: &ReadConfig) -> HidResult<BackendDevice> {
+    ensure!(!config.exclusive, HidError::custom("Exclusive access is not supported by the winrt backend"));
     let device = HidDevice::FromIdAsync(id, mode.into())?
         .await
         .on_null_result(|| HidError::custom(format!("Failed to open {}", id)))?;
     let input = match mode.readable() {
-        true => Some(InputReceiver::new(&device)?),
+        true => Some(InputReceiver::new(&device, interface_label(&id.to_string()), config)?),
         false => None
     };
-    Ok(BackendDevice { device, input })
+    let numbered_input_reports = config.numbered_reports(|| {
+        win32::get_report_ids(id, ReportType::Input).is_ok_and(|ids| ids.iter().any(|(id, _)| *id != 0))
+    });
+    Ok(BackendDevice {
+        id: id.clone(),
+        device,
+        input,
+        numbered_input_reports
+    })
 }
 
 impl BackendDevice {
-    pub async fn read_input_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+    pub fn is_connected(&self) -> bool {
+        is_connected(&self.id)
+    }
+
+    pub async fn read_input_report(&self, buf: &mut [u8]) -> HidResult<ReadOutcome> {
         let report = self
             .input
             .as_ref()
@@ -143,14 +265,156 @@ impl BackendDevice {
         let buffer = report.Data()?;
         let buffer = buffer.as_slice()?;
         ensure!(!buffer.is_empty(), HidError::custom("Input report is empty"));
+        // Windows prefixes unnumbered reports with a `0` byte
+        let (report_id, start) = match self.numbered_input_reports {
+            true => (NonZeroU8::new(buffer[0]), 0),
+            false => (None, 1)
+        };
+        let data = &buffer[start..];
+        let size = buf.len().min(data.len());
+        buf[..size].copy_from_slice(&data[..size]);
+
+        Ok(ReadOutcome {
+            copied: size,
+            report_len: data.len(),
+            report_id
+        })
+    }
+
+    pub async fn read_input_report_raw(&self, buf: &mut [u8]) -> HidResult<usize> {
+        let report = self
+            .input
+            .as_ref()
+            .expect("Reading is disabled")
+            .recv_async()
+            .await;
+        let buffer = report.Data()?;
+        let buffer = buffer.as_slice()?;
         let size = buf.len().min(buffer.len());
-        let start = if buffer[0] == 0x0 { 1 } else { 0 };
-        buf[..(size - start)].copy_from_slice(&buffer[start..size]);
+        buf[..size].copy_from_slice(&buffer[..size]);
+        Ok(size)
+    }
 
-        Ok(size - start)
+    pub async fn get_input_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        ensure!(!buf.is_empty(), HidError::zero_sized_data());
+        let report = self.device.GetInputReportByIdAsync(buf[0] as u16)?.await?;
+        let buffer = report.Data()?;
+        let buffer = buffer.as_slice()?;
+        let size = buf.len().min(buffer.len());
+        buf[..size].copy_from_slice(&buffer[..size]);
+        Ok(size)
     }
 
-    pub async fn write_output_report(&self, buf: &[u8]) -> HidResult<()> {
+    pub fn input_report_len(&self, _report_id: u8) -> HidResult<usize> {
+        win32::get_input_report_len(&self.id)
+    }
+
+    pub fn max_input_report_len(&self) -> HidResult<usize> {
+        win32::get_input_report_len(&self.id)
+    }
+
+    pub fn decode_input_report(&self, report: &[u8]) -> HidResult<Vec<(Usage, i64)>> {
+        win32::decode_input_report(&self.id, self.numbered_input_reports, report)
+    }
+
+    pub fn serial_number(&self) -> HidResult<Option<String>> {
+        // The serial number is always available through the DeviceInfo
+        Ok(None)
+    }
+
+    pub async fn control_transfer(&self, _request_type: u8, _request: u8, _value: u16, _index: u16, _data: &mut [u8]) -> HidResult<usize> {
+        Err(HidError::custom("Control transfers are not supported on this platform"))
+    }
+
+    pub async fn get_output_report(&self, _buf: &mut [u8]) -> HidResult<usize> {
+        Err(HidError::custom("Requesting output reports is not supported on this platform"))
+    }
+
+    pub async fn indexed_string(&self, index: u32) -> HidResult<Option<String>> {
+        Ok(Some(win32::get_indexed_string(&self.id, index)?).filter(|s| !s.is_empty()))
+    }
+
+    pub async fn read_feature_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        ensure!(!buf.is_empty(), HidError::zero_sized_data());
+        let report = self.device.GetFeatureReportByIdAsync(buf[0] as u16)?.await?;
+        let buffer = report.Data()?;
+        let buffer = buffer.as_slice()?;
+        let size = buf.len().min(buffer.len());
+        buf[..size].copy_from_slice(&buffer[..size]);
+        Ok(size)
+    }
+
+    pub async fn read_feature_report_timeout(&self, buf: &mut [u8], timeout: Duration) -> HidResult<Option<usize>> {
+        let read = async { Some(self.read_feature_report(buf).await) };
+        let timeout = async {
+            Delay::new(timeout).await;
+            None
+        };
+        read.or(timeout).await.transpose()
+    }
+
+    pub async fn write_feature_report(&self, buf: &[u8]) -> HidResult<()> {
+        ensure!(!buf.is_empty(), HidError::zero_sized_data());
+        let report = self.device.CreateFeatureReportById(buf[0] as u16)?;
+
+        {
+            let mut buffer = report.Data()?;
+            ensure!(buffer.Length()? as usize >= buf.len(), HidError::custom("Feature report is too large"));
+            let (buffer, remainder) = buffer.as_mut_slice()?.split_at_mut(buf.len());
+            buffer.copy_from_slice(buf);
+            remainder.fill(0);
+        }
+
+        self.device.SendFeatureReportAsync(&report)?.await?;
+        Ok(())
+    }
+
+    pub fn feature_report_len(&self, _report_id: u8) -> HidResult<usize> {
+        win32::get_feature_report_len(&self.id)
+    }
+
+    pub fn report_descriptor(&self) -> HidResult<Vec<u8>> {
+        // Windows only exposes the preparsed data, which can't be turned back into the original descriptor
+        Err(HidError::custom("Reading the report descriptor is not supported on this platform"))
+    }
+
+    pub fn feature_report_ids(&self) -> HidResult<Vec<(u8, usize)>> {
+        win32::get_report_ids(&self.id, ReportType::Feature)
+    }
+
+    pub fn input_report_ids(&self) -> HidResult<Vec<(u8, usize)>> {
+        win32::get_report_ids(&self.id, ReportType::Input)
+    }
+
+    pub fn output_report_ids(&self) -> HidResult<Vec<(u8, usize)>> {
+        win32::get_report_ids(&self.id, ReportType::Output)
+    }
+
+    pub fn usages(&self) -> HidResult<Vec<Usage>> {
+        // Every top-level collection is a separate device on Windows
+        Ok(vec![Usage::new(self.device.UsagePage()?, self.device.UsageId()?)])
+    }
+
+    pub fn collection_input_report_ids(&self, _usage_page: u16, _usage_id: u16) -> HidResult<Option<Vec<u8>>> {
+        // Every top-level collection is a separate device on Windows
+        Ok(None)
+    }
+
+    pub fn dropped_reports(&self) -> u64 {
+        self.input
+            .as_ref()
+            .map_or(0, |input| input.dropped.load(Ordering::Relaxed))
+    }
+
+    pub fn clear_input_reports(&self) -> HidResult<()> {
+        self.input
+            .as_ref()
+            .expect("Reading is disabled")
+            .clear();
+        Ok(())
+    }
+
+    fn create_output_report(&self, buf: &[u8]) -> HidResult<HidOutputReport> {
         ensure!(!buf.is_empty(), HidError::zero_sized_data());
         let report = self.device.CreateOutputReport()?;
 
@@ -162,21 +426,61 @@ impl BackendDevice {
             remainder.fill(0);
         }
 
+        Ok(report)
+    }
+
+    pub async fn write_output_report(&self, buf: &[u8]) -> HidResult<()> {
+        let report = self.create_output_report(buf)?;
         self.device.SendOutputReportAsync(&report)?.await?;
         Ok(())
     }
+
+    pub async fn write_output_reports(&self, reports: &[&[u8]]) -> HidResult<()> {
+        // All reports are submitted before waiting for the first one to complete
+        let operations = reports
+            .iter()
+            .map(|report| Ok(self.device.SendOutputReportAsync(&self.create_output_report(report)?)?))
+            .collect::<HidResult<Vec<_>>>()?;
+        for operation in operations {
+            operation.await?;
+        }
+        Ok(())
+    }
+
+    pub async fn write_output_report_timeout(&self, buf: &[u8], timeout: Duration) -> HidResult<bool> {
+        let report = self.create_output_report(buf)?;
+        let mut operation = self.device.SendOutputReportAsync(&report)?;
+        let write = async { Some((&mut operation).await) };
+        let timeout = async {
+            Delay::new(timeout).await;
+            None
+        };
+        match write.or(timeout).await {
+            Some(result) => result.map(|_| true).map_err(HidError::from),
+            None => {
+                // Dropping the future doesn't abort the operation, so it has to be canceled explicitly
+                operation.Cancel()?;
+                Ok(false)
+            }
+        }
+    }
 }
 
+// Both values are queried lazily, so they are simply queried again after deserialization
 #[derive(Default, Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BackendPrivateData {
-    serial_number: OnceLock<Option<String>>
+    #[cfg_attr(feature = "serde", serde(skip))]
+    serial_number: OnceLock<Option<String>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    container_id: OnceLock<Option<String>>
 }
 
 /// Wrapper type for HSTRING to add Hash implementation
 ///
 /// windows-rs has a built-in Hash HSTRING implementation after version 0.55.0 (introduced by this PR https://github.com/microsoft/windows-rs/pull/2924/files)
 /// Though, a direct upgrade to the newer windows-rs versions would require further work due to API and functionality changes
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct HashableHSTRING(HSTRING);
 
 impl Display for HashableHSTRING {
@@ -208,6 +512,10 @@ impl Hash for HashableHSTRING {
 pub type BackendDeviceId = HashableHSTRING;
 pub type BackendError = windows::core::Error;
 
+pub fn raw_os_error(err: &BackendError) -> Option<i32> {
+    Some(err.code().0)
+}
+
 impl From<BackendError> for ErrorSource {
     fn from(value: BackendError) -> Self {
         ErrorSource::PlatformSpecific(value)
@@ -224,34 +532,69 @@ impl From<AccessMode> for FileAccessMode {
     }
 }
 
-struct DeviceInformationSteam {
-    devices: DeviceInformationCollection,
-    index: u32
+/// Yields the devices found by a [DeviceWatcher] as soon as they are discovered
+///
+/// The stream ends once the watcher reports that the initial enumeration is completed.
+struct DeviceWatcherStream {
+    watcher: DeviceWatcher,
+    added_token: EventRegistrationToken,
+    completed_token: EventRegistrationToken,
+    // `None` marks the end of the enumeration
+    devices: RecvStream<'static, Option<DeviceInformation>>,
+    completed: bool
 }
 
-impl From<DeviceInformationCollection> for DeviceInformationSteam {
-    fn from(value: DeviceInformationCollection) -> Self {
-        Self {
-            devices: value,
-            index: 0,
-        }
+impl DeviceWatcherStream {
+    fn new(selector: &HSTRING) -> HidResult<Self> {
+        let watcher = DeviceInformation::CreateWatcherAqsFilter(selector)?;
+        let (sender, receiver) = flume::unbounded();
+        let added_sender = sender.clone();
+        let added_token = watcher.Added(&TypedEventHandler::new(move |_, info: &Option<DeviceInformation>| {
+            if let Some(info) = info {
+                let _ = added_sender.send(Some(info.clone()));
+            }
+            Ok(())
+        }))?;
+        let completed_token = watcher.EnumerationCompleted(&TypedEventHandler::new(move |_, _| {
+            let _ = sender.send(None);
+            Ok(())
+        }))?;
+        watcher.Start()?;
+        Ok(Self {
+            watcher,
+            added_token,
+            completed_token,
+            devices: receiver.into_stream(),
+            completed: false
+        })
     }
 }
 
-impl Stream for DeviceInformationSteam {
+impl Stream for DeviceWatcherStream {
     type Item = DeviceInformation;
 
-    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let current = self.index;
-        self.index += 1;
-        Poll::Ready(self.devices.GetAt(current).ok())
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.completed {
+            return Poll::Ready(None);
+        }
+        match ready!(self.devices.poll_next(cx)) {
+            Some(Some(info)) => Poll::Ready(Some(info)),
+            _ => {
+                self.completed = true;
+                Poll::Ready(None)
+            }
+        }
     }
+}
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let remaining = (self
-            .devices
-            .Size()
-            .expect("Failed to get the length of the collection") - self.index) as usize;
-        (remaining, Some(remaining))
+impl Drop for DeviceWatcherStream {
+    fn drop(&mut self) {
+        let _ = self.watcher.RemoveAdded(self.added_token);
+        let _ = self.watcher.RemoveEnumerationCompleted(self.completed_token);
+        if matches!(self.watcher.Status(), Ok(DeviceWatcherStatus::Started | DeviceWatcherStatus::EnumerationCompleted)) {
+            self.watcher
+                .Stop()
+                .unwrap_or_else(|err| log::warn!("Failed to stop the device watcher: {}", err));
+        }
     }
 }
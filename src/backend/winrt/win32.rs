@@ -1,13 +1,14 @@
+use std::ffi::c_void;
 use std::mem::size_of;
 
-use windows::core::PCWSTR;
-use windows::Win32::Devices::HumanInterfaceDevice::HidD_GetSerialNumberString;
-use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Devices::HumanInterfaceDevice::{HidD_FreePreparsedData, HidD_GetIndexedString, HidD_GetManufacturerString, HidD_GetPreparsedData, HidD_GetSerialNumberString, HidP_Feature, HidP_GetButtonCaps, HidP_GetCaps, HidP_GetValueCaps, HidP_Input, HidP_Output, HIDP_BUTTON_CAPS, HIDP_CAPS, HIDP_VALUE_CAPS, PHIDP_PREPARSED_DATA};
+use windows::Win32::Foundation::{CloseHandle, BOOLEAN, E_ACCESSDENIED, ERROR_SHARING_VIOLATION, HANDLE};
 use windows::Win32::Storage::FileSystem::{CreateFileW, FILE_FLAG_OVERLAPPED, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING};
 
-use crate::backend::BackendDeviceId;
+use crate::backend::{cfgmgr, hidp, BackendDeviceId};
 use crate::error::HidResult;
-use crate::{DeviceInfo, SerialNumberExt};
+use crate::{AccessState, BusType, DeviceInfo, ReportType, SerialNumberExt, Usage};
 
 impl SerialNumberExt for DeviceInfo {
     fn serial_number(&self) -> Option<&str> {
@@ -23,16 +24,154 @@ impl SerialNumberExt for DeviceInfo {
     }
 }
 
+impl DeviceInfo {
+    pub(crate) fn physical_device_id(&self) -> Option<&str> {
+        self.private_data
+            .container_id
+            .get_or_init(|| {
+                cfgmgr::get_container_id(PCWSTR::from_raw(self.id.0.as_ptr()))
+                    .map_err(|err| log::trace!("Failed to query the container id:\n\t{:?}", err))
+                    .ok()
+                    .map(|guid| format!("{guid:?}"))
+            })
+            .as_deref()
+    }
+}
+
+pub fn get_location(path: &HSTRING) -> Option<String> {
+    cfgmgr::get_location(PCWSTR::from_raw(path.as_ptr()))
+        .map_err(|err| log::trace!("Failed to query the location:\n\t{:?}", err))
+        .ok()
+}
+
+pub fn get_bus_type(path: &HSTRING) -> BusType {
+    cfgmgr::get_bus_type(PCWSTR::from_raw(path.as_ptr())).unwrap_or_else(|err| {
+        log::trace!("Failed to query the bus type:\n\t{:?}", err);
+        BusType::Unknown
+    })
+}
+
 fn get_serial_number(path: &BackendDeviceId) -> HidResult<String> {
+    read_string(path, HidD_GetSerialNumberString)
+}
+
+pub fn get_manufacturer(path: &HSTRING) -> Option<String> {
+    read_string(path, HidD_GetManufacturerString)
+        .map_err(|err| log::trace!("Failed to query the manufacturer:\n\t{:?}", err))
+        .ok()
+        .filter(|s| !s.is_empty())
+}
+
+pub fn get_indexed_string(path: &HSTRING, index: u32) -> HidResult<String> {
+    read_string_with(path, |handle, buffer, len| unsafe { HidD_GetIndexedString(handle, index, buffer, len) })
+}
+
+fn read_string(path: &HSTRING, func: unsafe fn(HANDLE, *mut c_void, u32) -> BOOLEAN) -> HidResult<String> {
+    read_string_with(path, |handle, buffer, len| unsafe { func(handle, buffer, len) })
+}
+
+fn read_string_with(path: &HSTRING, func: impl FnOnce(HANDLE, *mut c_void, u32) -> BOOLEAN) -> HidResult<String> {
     let handle = open_device(PCWSTR::from_raw(path.as_ptr()))?;
     let mut buffer = [0u16; 256];
-    unsafe { HidD_GetSerialNumberString(handle.as_raw(), buffer.as_mut_ptr() as _, (size_of::<u16>() * buffer.len()) as u32) }.ok()?;
-    let serial_number = buffer
+    func(handle.as_raw(), buffer.as_mut_ptr() as _, (size_of::<u16>() * buffer.len()) as u32).ok()?;
+    let string = buffer
         .split(|c| *c == 0x0)
         .map(String::from_utf16_lossy)
         .next()
         .expect("Failed to interpret string");
-    Ok(serial_number)
+    Ok(string)
+}
+
+pub fn get_input_report_len(path: &BackendDeviceId) -> HidResult<usize> {
+    with_preparsed_data(path, |preparsed_data| Ok(get_caps(preparsed_data)?.InputReportByteLength as usize))
+}
+
+pub fn decode_input_report(path: &BackendDeviceId, numbered: bool, report: &[u8]) -> HidResult<Vec<(Usage, i64)>> {
+    with_preparsed_data(path, |preparsed_data| hidp::decode_report(preparsed_data, ReportType::Input, numbered, report))
+}
+
+pub fn get_feature_report_len(path: &BackendDeviceId) -> HidResult<usize> {
+    with_preparsed_data(path, |preparsed_data| Ok(get_caps(preparsed_data)?.FeatureReportByteLength as usize))
+}
+
+pub fn get_report_ids(path: &BackendDeviceId, report_type: ReportType) -> HidResult<Vec<(u8, usize)>> {
+    with_preparsed_data(path, |preparsed_data| {
+        let caps = get_caps(preparsed_data)?;
+        let (hid_report_type, report_len, button_caps_len, value_caps_len) = match report_type {
+            ReportType::Input => (HidP_Input, caps.InputReportByteLength, caps.NumberInputButtonCaps, caps.NumberInputValueCaps),
+            ReportType::Output => (HidP_Output, caps.OutputReportByteLength, caps.NumberOutputButtonCaps, caps.NumberOutputValueCaps),
+            ReportType::Feature => (HidP_Feature, caps.FeatureReportByteLength, caps.NumberFeatureButtonCaps, caps.NumberFeatureValueCaps)
+        };
+        let mut button_caps = vec![HIDP_BUTTON_CAPS::default(); button_caps_len as usize];
+        let mut value_caps = vec![HIDP_VALUE_CAPS::default(); value_caps_len as usize];
+        let mut button_caps_len = button_caps_len;
+        let mut value_caps_len = value_caps_len;
+        unsafe {
+            if button_caps_len > 0 {
+                HidP_GetButtonCaps(hid_report_type, button_caps.as_mut_ptr(), &mut button_caps_len, preparsed_data).ok()?;
+            }
+            if value_caps_len > 0 {
+                HidP_GetValueCaps(hid_report_type, value_caps.as_mut_ptr(), &mut value_caps_len, preparsed_data).ok()?;
+            }
+        }
+        let mut ids = button_caps[..button_caps_len as usize]
+            .iter()
+            .map(|c| c.ReportID)
+            .chain(value_caps[..value_caps_len as usize].iter().map(|c| c.ReportID))
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+        // Windows always pads reports to the size of the largest one of the same type
+        Ok(ids
+            .into_iter()
+            .map(|id| (id, report_len as usize))
+            .collect())
+    })
+}
+
+fn get_caps(preparsed_data: PHIDP_PREPARSED_DATA) -> HidResult<HIDP_CAPS> {
+    let mut caps = HIDP_CAPS::default();
+    unsafe { HidP_GetCaps(preparsed_data, &mut caps) }.ok()?;
+    Ok(caps)
+}
+
+fn with_preparsed_data<T>(path: &BackendDeviceId, func: impl FnOnce(PHIDP_PREPARSED_DATA) -> HidResult<T>) -> HidResult<T> {
+    let handle = open_device(PCWSTR::from_raw(path.as_ptr()))?;
+    let mut preparsed_data = PHIDP_PREPARSED_DATA::default();
+    unsafe { HidD_GetPreparsedData(handle.as_raw(), &mut preparsed_data) }.ok()?;
+    let result = func(preparsed_data);
+    unsafe { HidD_FreePreparsedData(preparsed_data) }
+        .ok()
+        .unwrap_or_else(|err| log::warn!("Failed to free preparsed data: {}", err));
+    result
+}
+
+pub fn is_connected(path: &BackendDeviceId) -> bool {
+    // Opening the device without requesting any access doesn't interfere with the existing handles
+    open_device(PCWSTR::from_raw(path.as_ptr())).is_ok()
+}
+
+pub fn access_state(path: &BackendDeviceId) -> HidResult<AccessState> {
+    let result = unsafe {
+        CreateFileW(
+            PCWSTR::from_raw(path.as_ptr()),
+            (FILE_SHARE_READ | FILE_SHARE_WRITE).0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAG_OVERLAPPED,
+            HANDLE::default()
+        )
+    };
+    match result {
+        Ok(handle) => {
+            drop(Handle::from_raw(handle));
+            Ok(AccessState::Available)
+        }
+        Err(err) if err.code() == E_ACCESSDENIED => Ok(AccessState::PermissionDenied),
+        Err(err) if err.code() == ERROR_SHARING_VIOLATION.to_hresult() => Ok(AccessState::BusyExclusive),
+        Err(err) => Err(err.into())
+    }
 }
 
 fn open_device(path: PCWSTR) -> HidResult<Handle> {
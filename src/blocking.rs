@@ -0,0 +1,65 @@
+//! Synchronous wrappers around the async API for callers that can't run an executor
+//!
+//! Every method drives the corresponding async method to completion on the calling thread, so they can be used from threads
+//! without any reactor. On Linux this requires the `async-io` feature, as the `tokio` backend only works inside a tokio runtime.
+//! Mixing blocking and async calls on the same device at the same time is not supported.
+
+use std::time::Duration;
+
+use pollster::block_on;
+
+use crate::{AccessMode, Device, DeviceInfo, HidResult, OpenOptions, ReadConfig};
+
+impl DeviceInfo {
+    /// Blocking version of [DeviceInfo::enumerate_all]
+    pub fn enumerate_blocking() -> HidResult<Vec<DeviceInfo>> {
+        block_on(DeviceInfo::enumerate_all())
+    }
+
+    /// Blocking version of [DeviceInfo::open]
+    pub fn open_blocking(&self, mode: AccessMode) -> HidResult<Device> {
+        block_on(self.open(mode))
+    }
+
+    /// Blocking version of [DeviceInfo::open_with_config]
+    pub fn open_with_config_blocking(&self, mode: AccessMode, config: ReadConfig) -> HidResult<Device> {
+        block_on(self.open_with_config(mode, config))
+    }
+
+    /// Blocking version of [DeviceInfo::open_with]
+    pub fn open_with_blocking(&self, options: OpenOptions) -> HidResult<Device> {
+        block_on(self.open_with(options))
+    }
+}
+
+impl Device {
+    /// Blocking version of [Device::read_input_report]
+    pub fn read_input_report_blocking(&self, buf: &mut [u8]) -> HidResult<usize> {
+        block_on(self.read_input_report(buf))
+    }
+
+    /// Blocking version of [Device::read_input_report_timeout]
+    pub fn read_input_report_timeout_blocking(&self, buf: &mut [u8], timeout: Duration) -> HidResult<Option<usize>> {
+        block_on(self.read_input_report_timeout(buf, timeout))
+    }
+
+    /// Blocking version of [Device::write_output_report]
+    pub fn write_output_report_blocking(&self, buf: &[u8]) -> HidResult<()> {
+        block_on(self.write_output_report(buf))
+    }
+
+    /// Blocking version of [Device::get_input_report]
+    pub fn get_input_report_blocking(&self, buf: &mut [u8]) -> HidResult<usize> {
+        block_on(self.get_input_report(buf))
+    }
+
+    /// Blocking version of [Device::read_feature_report]
+    pub fn read_feature_report_blocking(&self, buf: &mut [u8]) -> HidResult<usize> {
+        block_on(self.read_feature_report(buf))
+    }
+
+    /// Blocking version of [Device::write_feature_report]
+    pub fn write_feature_report_blocking(&self, buf: &[u8]) -> HidResult<()> {
+        block_on(self.write_feature_report(buf))
+    }
+}
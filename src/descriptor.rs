@@ -0,0 +1,772 @@
+// The Windows backends rely on the preparsed data instead, so only the public parser is used there
+#![cfg_attr(target_os = "windows", allow(dead_code))]
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Cursor, Read};
+
+use crate::report::sign_extend;
+use crate::{ensure, HidError, HidResult, ReportType, Usage};
+
+/// A raw HID report descriptor
+#[derive(Debug, Default)]
+pub(crate) struct ReportDescriptor(Vec<u8>);
+
+/// The reports declared in a report descriptor
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct ParsedDescriptor {
+    /// Every report in ascending order of type and id
+    pub reports: Vec<ReportInfo>
+}
+
+impl ParsedDescriptor {
+    /// Parses the id and size of every report declared in the given descriptor, i.e. the bytes returned by [crate::Device::report_descriptor]
+    ///
+    /// Fails if the descriptor contains a malformed item.
+    pub fn parse(bytes: &[u8]) -> HidResult<Self> {
+        let mut bits = BTreeMap::new();
+        next_report_sizes(&mut Cursor::new(bytes), &mut bits).ok_or(HidError::custom("Malformed report descriptor"))?;
+        let reports = bits
+            .into_iter()
+            .map(|((kind, id), bit_size)| ReportInfo { id, kind, bit_size })
+            .collect();
+        Ok(ParsedDescriptor { reports })
+    }
+
+    /// Returns the size in bytes of the given report **excluding** the report id
+    pub fn report_size(&self, kind: ReportType, id: u8) -> Option<usize> {
+        self.reports
+            .iter()
+            .find(|report| report.kind == kind && report.id == id)
+            .map(ReportInfo::byte_size)
+    }
+}
+
+/// The id and size of a single report
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ReportInfo {
+    /// The report id or `0` if the device doesn't use numbered reports
+    pub id: u8,
+    /// Whether this is an input, output or feature report
+    pub kind: ReportType,
+    /// The combined size of all fields of this report in bits **excluding** the report id
+    pub bit_size: usize
+}
+
+impl ReportInfo {
+    /// The size of this report in bytes **excluding** the report id
+    pub fn byte_size(&self) -> usize {
+        self.bit_size.div_ceil(8)
+    }
+}
+
+impl ReportDescriptor {
+    /// Create a descriptor from a vec
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub(crate) fn from_vec(value: Vec<u8>) -> Self {
+        ReportDescriptor(value)
+    }
+
+    /// Create a descriptor from a slice
+    ///
+    /// It returns an error if the value slice is too large for it to be a HID
+    /// descriptor
+    pub(crate) fn from_slice(value: &[u8]) -> HidResult<Self> {
+        Ok(ReportDescriptor(value.to_vec()))
+    }
+
+    /// The raw bytes of this descriptor
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub(crate) fn usages(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        UsageIterator {
+            usage_page: 0,
+            cursor: Cursor::new(self.0.as_slice())
+        }
+    }
+
+    /// Calculates the size of every report declared in this descriptor
+    ///
+    /// The sizes are in bytes and **exclude** the report id.
+    /// Unlike [ParsedDescriptor::parse] this keeps the reports declared before the first malformed item, so that devices
+    /// with a slightly broken descriptor can still be used.
+    pub(crate) fn report_sizes(&self) -> BTreeMap<(ReportType, u8), usize> {
+        let mut bits = BTreeMap::new();
+        if next_report_sizes(&mut Cursor::new(self.0.as_slice()), &mut bits).is_none() {
+            log::debug!("Report descriptor is malformed, only the reports declared before the broken item are used");
+        }
+        bits.into_iter()
+            .map(|(key, bits)| (key, bits.div_ceil(8)))
+            .collect()
+    }
+
+    /// Calculates the size in bytes of a specific report **excluding** the report id
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub(crate) fn report_size(&self, report_type: ReportType, report_id: u8) -> Option<usize> {
+        self.report_sizes()
+            .get(&(report_type, report_id))
+            .copied()
+    }
+
+    /// Lists the ids of all reports of the given type that are declared inside a collection with the given usage
+    pub(crate) fn collection_report_ids(&self, report_type: ReportType, usage_page: u16, usage: u16) -> Vec<u8> {
+        let mut ids = BTreeSet::new();
+        next_collection_report_ids(&mut Cursor::new(self.0.as_slice()), report_type, (usage_page, usage), &mut ids);
+        ids.into_iter().collect()
+    }
+
+    /// Parses the layout of every main item declared in this descriptor
+    pub(crate) fn fields(&self) -> Vec<ReportField> {
+        let mut fields = Vec::new();
+        next_report_fields(&mut Cursor::new(self.0.as_slice()), &mut fields);
+        fields
+    }
+
+    /// Extracts the value of every field of the given report
+    ///
+    /// `report` must start with the report id if `numbered` is set, which should reflect how the reports were actually read
+    /// (see [ReadConfig::report_id_mode](crate::ReadConfig::report_id_mode)) instead of what the descriptor declares.
+    /// Variable fields yield their current value, while array fields (e.g. the keys of a keyboard) only yield
+    /// the usages that are currently active with a value of `1`.
+    pub(crate) fn decode(&self, report_type: ReportType, numbered: bool, report: &[u8]) -> HidResult<Vec<(Usage, i64)>> {
+        let fields = self.fields();
+        let (report_id, data) = match numbered {
+            true => report
+                .split_first()
+                .map(|(id, data)| (*id, data))
+                .ok_or(HidError::zero_sized_data())?,
+            false => (0, report)
+        };
+
+        let mut values = Vec::new();
+        for field in fields
+            .iter()
+            .filter(|field| field.report_type == report_type && field.report_id == report_id && !field.is_constant())
+        {
+            ensure!(
+                field.bit_offset + field.count * field.bit_size <= data.len() * 8,
+                HidError::custom("Report is shorter than described by the report descriptor")
+            );
+            for i in 0..field.count {
+                let raw = read_bits(data, field.bit_offset + i * field.bit_size, field.bit_size)
+                    .ok_or(HidError::custom("Report is shorter than described by the report descriptor"))?;
+                let value = match field.logical_min < 0 {
+                    true => sign_extend(raw, field.bit_size),
+                    false => raw as i64
+                };
+                if field.is_variable() {
+                    // Values outside the logical range indicate that the control has no meaningful value (e.g. a centered hat switch)
+                    if field.has_null_state() && !(field.logical_min..=field.logical_max).contains(&value) {
+                        continue;
+                    }
+                    // Surplus fields reuse the last usage
+                    if let Some(usage) = field.usages.get(i).or(field.usages.last()) {
+                        values.push((*usage, value));
+                    }
+                } else {
+                    // Array fields contain indices into the usage list, where usage id 0 means "no event"
+                    let usage = usize::try_from(value - field.logical_min)
+                        .ok()
+                        .and_then(|index| field.usages.get(index))
+                        .filter(|usage| usage.id != 0);
+                    if let Some(usage) = usage {
+                        values.push((*usage, 1));
+                    }
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    /// Lists the id and size of every report of the given type
+    ///
+    /// Unlike [ReportDescriptor::report_sizes] the sizes **include** the report id.
+    pub(crate) fn report_lengths(&self, report_type: ReportType) -> Vec<(u8, usize)> {
+        self.report_sizes()
+            .into_iter()
+            .filter(|((kind, _), _)| *kind == report_type)
+            .map(|((_, id), size)| (id, size + 1))
+            .collect()
+    }
+}
+
+/// Iterates over the values in a ReportDescriptor
+struct UsageIterator<'a> {
+    usage_page: u16,
+    cursor: Cursor<&'a [u8]>
+}
+
+impl<'a> Iterator for UsageIterator<'a> {
+    type Item = (u16, u16);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (usage_page, page) = next_hid_usage(&mut self.cursor, self.usage_page)?;
+
+        self.usage_page = usage_page;
+        Some((usage_page, page))
+    }
+}
+
+// This comes from hidapi which apparently comes from Apple's implementation of
+// this
+fn next_hid_usage(cursor: &mut Cursor<&[u8]>, mut usage_page: u16) -> Option<(u16, u16)> {
+    let mut usage = None;
+    let mut usage_pair = None;
+    let initial = cursor.position() == 0;
+
+    while let Some(Ok(key)) = cursor.bytes().next() {
+        // The amount to skip is calculated based off of the start of the
+        // iteration so we need to keep track of that.
+        let position = cursor.position() - 1;
+        let key_cmd = key & 0xfc;
+
+        let (data_len, key_size) = hid_item_size(key, cursor)?;
+
+        match key_cmd {
+            // Usage Page 6.2.2.7 (Global)
+            0x4 => {
+                usage_page = match hid_report_bytes(cursor, data_len) {
+                    Ok(v) => v as u16,
+                    Err(_) => break,
+                }
+            }
+            // Usage 6.2.2.8 (Local)
+            0x8 => {
+                usage = match hid_report_bytes(cursor, data_len) {
+                    Ok(v) => Some(v as u16),
+                    Err(_) => break,
+                }
+            }
+            // Collection 6.2.2.4 (Main)
+            0xa0 => {
+                // Usage is a Local Item, unset it
+                if let Some(u) = usage.take() {
+                    usage_pair = Some((usage_page, u))
+                }
+            }
+            // Input 6.2.2.4 (Main)
+            0x80 |
+            // Output 6.2.2.4 (Main)
+            0x90 |
+            // Feature 6.2.2.4 (Main)
+            0xb0 |
+            // End Collection 6.2.2.4 (Main)
+            0xc0  =>  {
+                // Usage is a Local Item, unset it
+                usage.take();
+            }
+            _ => {}
+        }
+
+        skip_item(cursor, position, data_len, key_size)?;
+
+        if let Some((usage_page, usage)) = usage_pair {
+            return Some((usage_page, usage));
+        }
+    }
+
+    if let (true, Some(usage)) = (initial, usage) {
+        return Some((usage_page, usage));
+    }
+
+    None
+}
+
+/// The subset of the global item state that is relevant for calculating report sizes
+#[derive(Default, Copy, Clone)]
+struct ReportGlobals {
+    report_size: u32,
+    report_count: u32,
+    report_id: u8
+}
+
+/// Accumulates the size in bits of every report until the end of the descriptor or the first malformed item
+fn next_report_sizes(cursor: &mut Cursor<&[u8]>, bits: &mut BTreeMap<(ReportType, u8), usize>) -> Option<()> {
+    let mut globals = ReportGlobals::default();
+    let mut stack = Vec::new();
+
+    while let Some(Ok(key)) = cursor.bytes().next() {
+        let position = cursor.position() - 1;
+        let key_cmd = key & 0xfc;
+
+        let (data_len, key_size) = hid_item_size(key, cursor)?;
+
+        match key_cmd {
+            // Report Size 6.2.2.7 (Global)
+            0x74 => globals.report_size = hid_report_bytes(cursor, data_len).ok()?,
+            // Report ID 6.2.2.7 (Global)
+            0x84 => globals.report_id = hid_report_bytes(cursor, data_len).ok()? as u8,
+            // Report Count 6.2.2.7 (Global)
+            0x94 => globals.report_count = hid_report_bytes(cursor, data_len).ok()?,
+            // Push 6.2.2.7 (Global)
+            0xa4 => stack.push(globals),
+            // Pop 6.2.2.7 (Global)
+            0xb4 => globals = stack.pop()?,
+            // Input 6.2.2.4 (Main)
+            0x80 |
+            // Output 6.2.2.4 (Main)
+            0x90 |
+            // Feature 6.2.2.4 (Main)
+            0xb0 => {
+                let report_type = match key_cmd {
+                    0x80 => ReportType::Input,
+                    0x90 => ReportType::Output,
+                    _ => ReportType::Feature
+                };
+                let size = (globals.report_size as usize).checked_mul(globals.report_count as usize)?;
+                let total = bits.entry((report_type, globals.report_id)).or_insert(0);
+                *total = total.checked_add(size)?;
+            }
+            _ => {}
+        }
+
+        skip_item(cursor, position, data_len, key_size)?;
+    }
+
+    Some(())
+}
+
+/// Collects the report ids of all main items of the given type that are nested inside a collection with the target usage
+fn next_collection_report_ids(cursor: &mut Cursor<&[u8]>, report_type: ReportType, target: (u16, u16), ids: &mut BTreeSet<u8>) -> Option<()> {
+    let mut usage_page = 0;
+    let mut usage = None;
+    let mut report_id = 0;
+    let mut globals_stack = Vec::new();
+    // One entry per open collection, indicating if it (or one of its parents) matches the target usage
+    let mut collection_stack = Vec::new();
+
+    while let Some(Ok(key)) = cursor.bytes().next() {
+        let position = cursor.position() - 1;
+        let key_cmd = key & 0xfc;
+
+        let (data_len, key_size) = hid_item_size(key, cursor)?;
+
+        match key_cmd {
+            // Usage Page 6.2.2.7 (Global)
+            0x4 => usage_page = hid_report_bytes(cursor, data_len).ok()? as u16,
+            // Report ID 6.2.2.7 (Global)
+            0x84 => report_id = hid_report_bytes(cursor, data_len).ok()? as u8,
+            // Push 6.2.2.7 (Global)
+            0xa4 => globals_stack.push((usage_page, report_id)),
+            // Pop 6.2.2.7 (Global)
+            0xb4 => (usage_page, report_id) = globals_stack.pop()?,
+            // Usage 6.2.2.8 (Local)
+            0x8 => {
+                let value = hid_report_bytes(cursor, data_len).ok()?;
+                // Four byte usages contain the usage page in the upper half
+                usage = Some(match data_len {
+                    4 => ((value >> 16) as u16, value as u16),
+                    _ => (usage_page, value as u16)
+                });
+            }
+            // Collection 6.2.2.4 (Main)
+            0xa0 => {
+                let parent_matches = collection_stack.last().copied().unwrap_or(false);
+                collection_stack.push(parent_matches || usage.take() == Some(target));
+            }
+            // End Collection 6.2.2.4 (Main)
+            0xc0 => {
+                collection_stack.pop();
+                usage.take();
+            }
+            // Input 6.2.2.4 (Main)
+            0x80 |
+            // Output 6.2.2.4 (Main)
+            0x90 |
+            // Feature 6.2.2.4 (Main)
+            0xb0 => {
+                let item_type = match key_cmd {
+                    0x80 => ReportType::Input,
+                    0x90 => ReportType::Output,
+                    _ => ReportType::Feature
+                };
+                if item_type == report_type && collection_stack.last().copied().unwrap_or(false) {
+                    ids.insert(report_id);
+                }
+                usage.take();
+            }
+            _ => {}
+        }
+
+        skip_item(cursor, position, data_len, key_size)?;
+    }
+
+    Some(())
+}
+
+/// The layout of the data described by a single main item
+#[derive(Debug, Clone)]
+pub struct ReportField {
+    pub report_type: ReportType,
+    pub report_id: u8,
+    /// The position of the first value in bits, **excluding** the report id
+    pub bit_offset: usize,
+    /// The size of a single value in bits
+    pub bit_size: usize,
+    /// The number of values
+    pub count: usize,
+    pub logical_min: i64,
+    pub logical_max: i64,
+    /// The data flags of the main item (6.2.2.5)
+    pub flags: u32,
+    /// The usages of the values for variable fields or the possible usages for array fields
+    pub usages: Vec<Usage>
+}
+
+impl ReportField {
+    /// Constant fields are usually padding and don't contain any meaningful data
+    pub fn is_constant(&self) -> bool {
+        self.flags & 0x1 != 0
+    }
+
+    /// Variable fields contain one value per usage, while array fields contain indices of active usages
+    pub fn is_variable(&self) -> bool {
+        self.flags & 0x2 != 0
+    }
+
+    /// Fields with a null state can report values outside the logical range to indicate that there is no meaningful value
+    pub fn has_null_state(&self) -> bool {
+        self.flags & 0x40 != 0
+    }
+}
+
+/// The subset of the global item state that is relevant for the layout of report fields
+#[derive(Default, Copy, Clone)]
+struct FieldGlobals {
+    usage_page: u16,
+    logical_min: i32,
+    logical_max: i32,
+    // Some devices encode an unsigned maximum in too few bytes, so the unsigned interpretation is kept as fallback
+    logical_max_unsigned: u32,
+    report_size: u32,
+    report_count: u32,
+    report_id: u8
+}
+
+/// Collects the layout of every main item until the end of the descriptor or the first malformed item
+fn next_report_fields(cursor: &mut Cursor<&[u8]>, fields: &mut Vec<ReportField>) -> Option<()> {
+    let mut globals = FieldGlobals::default();
+    let mut stack = Vec::new();
+    let mut usages = Vec::new();
+    let mut usage_min = None;
+    let mut offsets = BTreeMap::new();
+
+    while let Some(Ok(key)) = cursor.bytes().next() {
+        let position = cursor.position() - 1;
+        let key_cmd = key & 0xfc;
+
+        let (data_len, key_size) = hid_item_size(key, cursor)?;
+
+        match key_cmd {
+            // Usage Page 6.2.2.7 (Global)
+            0x4 => globals.usage_page = hid_report_bytes(cursor, data_len).ok()? as u16,
+            // Logical Minimum 6.2.2.7 (Global)
+            0x14 => globals.logical_min = sign_extend(hid_report_bytes(cursor, data_len).ok()? as u64, data_len * 8) as i32,
+            // Logical Maximum 6.2.2.7 (Global)
+            0x24 => {
+                globals.logical_max_unsigned = hid_report_bytes(cursor, data_len).ok()?;
+                globals.logical_max = sign_extend(globals.logical_max_unsigned as u64, data_len * 8) as i32;
+            }
+            // Report Size 6.2.2.7 (Global)
+            0x74 => globals.report_size = hid_report_bytes(cursor, data_len).ok()?,
+            // Report ID 6.2.2.7 (Global)
+            0x84 => globals.report_id = hid_report_bytes(cursor, data_len).ok()? as u8,
+            // Report Count 6.2.2.7 (Global)
+            0x94 => globals.report_count = hid_report_bytes(cursor, data_len).ok()?,
+            // Push 6.2.2.7 (Global)
+            0xa4 => stack.push(globals),
+            // Pop 6.2.2.7 (Global)
+            0xb4 => globals = stack.pop()?,
+            // Usage 6.2.2.8 (Local)
+            0x8 => usages.push(hid_report_usage(cursor, data_len, globals.usage_page).ok()?),
+            // Usage Minimum 6.2.2.8 (Local)
+            0x18 => usage_min = Some(hid_report_usage(cursor, data_len, globals.usage_page).ok()?),
+            // Usage Maximum 6.2.2.8 (Local)
+            0x28 => {
+                let usage_max = hid_report_usage(cursor, data_len, globals.usage_page).ok()?;
+                if let Some(usage_min) = usage_min.take() {
+                    usages.extend((usage_min.id..=usage_max.id).map(|id| Usage::new(usage_min.page, id)));
+                }
+            }
+            // Input 6.2.2.4 (Main)
+            0x80 |
+            // Output 6.2.2.4 (Main)
+            0x90 |
+            // Feature 6.2.2.4 (Main)
+            0xb0 => {
+                let report_type = match key_cmd {
+                    0x80 => ReportType::Input,
+                    0x90 => ReportType::Output,
+                    _ => ReportType::Feature
+                };
+                let offset = offsets.entry((report_type, globals.report_id)).or_insert(0);
+                let field = ReportField {
+                    report_type,
+                    report_id: globals.report_id,
+                    bit_offset: *offset,
+                    bit_size: globals.report_size as usize,
+                    count: globals.report_count as usize,
+                    logical_min: globals.logical_min as i64,
+                    logical_max: match globals.logical_max < globals.logical_min {
+                        true => globals.logical_max_unsigned as i64,
+                        false => globals.logical_max as i64
+                    },
+                    flags: hid_report_bytes(cursor, data_len).ok()?,
+                    usages: std::mem::take(&mut usages)
+                };
+                *offset = field.bit_size.checked_mul(field.count).and_then(|size| offset.checked_add(size))?;
+                // Fields without any bits carry no data, but decoding would still iterate over their (possibly huge) count
+                if field.bit_size > 0 {
+                    fields.push(field);
+                }
+                usage_min = None;
+            }
+            // Collection 6.2.2.4 (Main)
+            0xa0 |
+            // End Collection 6.2.2.4 (Main)
+            0xc0 => {
+                // Usages are Local Items, unset them
+                usages.clear();
+                usage_min = None;
+            }
+            _ => {}
+        }
+
+        skip_item(cursor, position, data_len, key_size)?;
+    }
+
+    Some(())
+}
+
+/// Reads `size` bits starting at the bit `offset` from a little endian buffer
+fn read_bits(data: &[u8], offset: usize, size: usize) -> Option<u64> {
+    if size > 64 || (offset + size).div_ceil(8) > data.len() {
+        return None;
+    }
+    Some((0..size).fold(0, |value, i| {
+        let bit = offset + i;
+        value | (((data[bit / 8] >> (bit % 8)) & 0x1) as u64) << i
+    }))
+}
+
+/// Moves the cursor to the item following the one that starts at `position`
+///
+/// Fails if the current item extends past the end of the descriptor.
+fn skip_item(cursor: &mut Cursor<&[u8]>, position: u64, data_len: usize, key_size: usize) -> Option<()> {
+    let next = position + (data_len + key_size) as u64;
+    if next > cursor.get_ref().len() as u64 {
+        return None;
+    }
+    cursor.set_position(next);
+    Some(())
+}
+
+/// Gets the size of the HID item at the given position
+///
+/// Returns data_len and key_size when successful
+fn hid_item_size(key: u8, cursor: &mut Cursor<&[u8]>) -> Option<(usize, usize)> {
+    // Long Item. Next byte contains the length of the data section.
+    if (key & 0xf0) == 0xf0 {
+        if let Some(Ok(len)) = cursor.bytes().next() {
+            return Some((len.into(), 3));
+        }
+
+        // Malformed report
+        return None;
+    }
+
+    // Short Item. Bottom two bits contains the size code
+    match key & 0x03 {
+        v @ 0..=2 => Some((v.into(), 1)),
+        3 => Some((4, 1)),
+        _ => unreachable!() // & 0x03 means this can't happen
+    }
+}
+
+/// Get the bytes from a HID report descriptor
+///
+/// Must only be called with `num_bytes` 0, 1, 2 or 4.
+fn hid_report_bytes(cursor: &mut Cursor<&[u8]>, num_bytes: usize) -> std::io::Result<u32> {
+    let mut bytes: [u8; 4] = [0; 4];
+    cursor.read_exact(&mut bytes[..num_bytes])?;
+
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Get a usage from a HID report descriptor
+///
+/// Four byte usages contain the usage page in the upper half, otherwise the current usage page is used.
+fn hid_report_usage(cursor: &mut Cursor<&[u8]>, num_bytes: usize, usage_page: u16) -> std::io::Result<Usage> {
+    let value = hid_report_bytes(cursor, num_bytes)?;
+    Ok(match num_bytes {
+        4 => Usage::new((value >> 16) as u16, value as u16),
+        _ => Usage::new(usage_page, value as u16)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The boot keyboard from appendix B.1 of the HID specification
+    const KEYBOARD: &[u8] = &[
+        0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, 0x05, 0x07, 0x19, 0xE0, 0x29, 0xE7, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x08, 0x81, 0x02,
+        0x95, 0x01, 0x75, 0x08, 0x81, 0x01, 0x95, 0x05, 0x75, 0x01, 0x05, 0x08, 0x19, 0x01, 0x29, 0x05, 0x91, 0x02, 0x95, 0x01, 0x75, 0x03,
+        0x91, 0x01, 0x95, 0x06, 0x75, 0x08, 0x15, 0x00, 0x25, 0x65, 0x05, 0x07, 0x19, 0x00, 0x29, 0x65, 0x81, 0x00, 0xC0
+    ];
+
+    /// The boot mouse from appendix B.2 of the HID specification
+    const MOUSE: &[u8] = &[
+        0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x09, 0x01, 0xA1, 0x00, 0x05, 0x09, 0x19, 0x01, 0x29, 0x03, 0x15, 0x00, 0x25, 0x01, 0x95, 0x03,
+        0x75, 0x01, 0x81, 0x02, 0x95, 0x01, 0x75, 0x05, 0x81, 0x01, 0x05, 0x01, 0x09, 0x30, 0x09, 0x31, 0x15, 0x81, 0x25, 0x7F, 0x75, 0x08,
+        0x95, 0x02, 0x81, 0x06, 0xC0, 0xC0
+    ];
+
+    /// A keyboard (id 1), a consumer control (id 2) and a vendor defined feature report (id 3) in a single descriptor
+    const COMPOSITE: &[u8] = &[
+        0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, 0x85, 0x01, 0x05, 0x07, 0x19, 0xE0, 0x29, 0xE7, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x08,
+        0x81, 0x02, 0x95, 0x06, 0x75, 0x08, 0x15, 0x00, 0x25, 0x65, 0x19, 0x00, 0x29, 0x65, 0x81, 0x00, 0xC0, 0x05, 0x0C, 0x09, 0x01, 0xA1,
+        0x01, 0x85, 0x02, 0x15, 0x00, 0x26, 0xFF, 0x03, 0x19, 0x00, 0x2A, 0xFF, 0x03, 0x75, 0x10, 0x95, 0x01, 0x81, 0x00, 0xC0, 0x06, 0x00,
+        0xFF, 0x09, 0x01, 0xA1, 0x01, 0x85, 0x03, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x75, 0x08, 0x95, 0x3F, 0xB1, 0x02, 0xC0
+    ];
+
+    fn report(kind: ReportType, id: u8, bit_size: usize) -> ReportInfo {
+        ReportInfo { id, kind, bit_size }
+    }
+
+    #[test]
+    fn parse_keyboard() {
+        let parsed = ParsedDescriptor::parse(KEYBOARD).unwrap();
+        assert_eq!(parsed.reports, vec![report(ReportType::Input, 0, 64), report(ReportType::Output, 0, 8)]);
+        assert_eq!(parsed.report_size(ReportType::Input, 0), Some(8));
+        assert_eq!(parsed.report_size(ReportType::Output, 0), Some(1));
+        assert_eq!(parsed.report_size(ReportType::Feature, 0), None);
+    }
+
+    #[test]
+    fn parse_mouse() {
+        let parsed = ParsedDescriptor::parse(MOUSE).unwrap();
+        assert_eq!(parsed.reports, vec![report(ReportType::Input, 0, 24)]);
+    }
+
+    #[test]
+    fn parse_composite() {
+        let parsed = ParsedDescriptor::parse(COMPOSITE).unwrap();
+        assert_eq!(
+            parsed.reports,
+            vec![report(ReportType::Input, 1, 56), report(ReportType::Input, 2, 16), report(ReportType::Feature, 3, 504)]
+        );
+        assert_eq!(parsed.report_size(ReportType::Feature, 3), Some(63));
+        assert_eq!(parsed.report_size(ReportType::Input, 0), None);
+    }
+
+    #[test]
+    fn parse_malformed() {
+        // Short item without its data byte
+        assert!(ParsedDescriptor::parse(&[0x75]).is_err());
+        // Truncated item that doesn't affect the report sizes
+        assert!(ParsedDescriptor::parse(&[0x05, 0x01, 0x09]).is_err());
+        // Long item without its length byte
+        assert!(ParsedDescriptor::parse(&[0xFE]).is_err());
+        // Long item that is longer than the remaining descriptor
+        assert!(ParsedDescriptor::parse(&[0xFE, 0x10, 0x00, 0x01]).is_err());
+        // Pop without a matching push
+        assert!(ParsedDescriptor::parse(&[0xB4]).is_err());
+        // Report sizes that overflow when added up
+        assert!(ParsedDescriptor::parse(&[
+            0x77, 0xFF, 0xFF, 0xFF, 0xFF, 0x97, 0xFF, 0xFF, 0xFF, 0xFF, 0x81, 0x00, 0x81, 0x00, 0x81, 0x00, 0x81, 0x00
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn parse_truncated() {
+        for len in 0..COMPOSITE.len() {
+            // Cutting the descriptor between two items is valid, cutting it inside of an item is not
+            let result = ParsedDescriptor::parse(&COMPOSITE[..len]);
+            let mut position = 0;
+            while position < len {
+                let (data_len, key_size) = hid_item_size(COMPOSITE[position], &mut Cursor::new(&COMPOSITE[position + 1..])).unwrap();
+                position += data_len + key_size;
+            }
+            assert_eq!(result.is_ok(), position == len, "prefix of length {len}");
+        }
+        assert!(ParsedDescriptor::parse(&[]).unwrap().reports.is_empty());
+    }
+
+    /// A hat switch with a null state followed by four bits of padding
+    const HAT_SWITCH: &[u8] = &[
+        0x05, 0x01, 0x09, 0x39, 0x15, 0x00, 0x25, 0x07, 0x35, 0x00, 0x46, 0x3B, 0x01, 0x65, 0x14, 0x75, 0x04, 0x95, 0x01, 0x81, 0x42,
+        0x75, 0x04, 0x95, 0x01, 0x81, 0x01
+    ];
+
+    fn decode(descriptor: &[u8], numbered: bool, report: &[u8]) -> HidResult<Vec<(Usage, i64)>> {
+        ReportDescriptor::from_slice(descriptor)?.decode(ReportType::Input, numbered, report)
+    }
+
+    #[test]
+    fn bits() {
+        assert_eq!(read_bits(&[0b1010_1100], 2, 4), Some(0b1011));
+        assert_eq!(read_bits(&[0xF0, 0x0F], 4, 8), Some(0xFF));
+        assert_eq!(read_bits(&[0x34, 0x12], 0, 16), Some(0x1234));
+        assert_eq!(read_bits(&[0xFF], 3, 0), Some(0));
+        assert_eq!(read_bits(&[0xFF], 4, 8), None);
+        assert_eq!(read_bits(&[0xFF; 9], 0, 65), None);
+    }
+
+    #[test]
+    fn decode_sign_extension() {
+        let values = decode(MOUSE, false, &[0b101, 0xFF, 0x05]).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                (Usage::new(0x09, 0x01), 1),
+                (Usage::new(0x09, 0x02), 0),
+                (Usage::new(0x09, 0x03), 1),
+                (Usage::new(0x01, 0x30), -1),
+                (Usage::new(0x01, 0x31), 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_null_state() {
+        assert_eq!(decode(HAT_SWITCH, false, &[0x03]).unwrap(), vec![(Usage::new(0x01, 0x39), 3)]);
+        // A centered hat switch reports a value outside of the logical range
+        assert_eq!(decode(HAT_SWITCH, false, &[0x08]).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn decode_array() {
+        let values = decode(KEYBOARD, false, &[0x02, 0x00, 0x04, 0x05, 0x00, 0x00, 0x00, 0x00]).unwrap();
+        let modifiers = (0xE0..=0xE7).map(|id| (Usage::new(0x07, id), i64::from(id == 0xE1)));
+        let keys = [(Usage::new(0x07, 0x04), 1), (Usage::new(0x07, 0x05), 1)];
+        assert_eq!(values, modifiers.chain(keys).collect::<Vec<_>>());
+        assert!(decode(KEYBOARD, false, &[0x00; 4]).is_err());
+    }
+
+    #[test]
+    fn decode_empty_fields() {
+        // A field of four billion values without any bits followed by a regular byte
+        let descriptor = [
+            0x05, 0x01, 0x09, 0x30, 0x15, 0x00, 0x25, 0x7F, 0x75, 0x00, 0x97, 0xFF, 0xFF, 0xFF, 0xFF, 0x81, 0x02, 0x75, 0x08, 0x95, 0x01,
+            0x09, 0x31, 0x81, 0x02
+        ];
+        assert_eq!(decode(&descriptor, false, &[0x05]).unwrap(), vec![(Usage::new(0x01, 0x31), 5)]);
+        assert!(decode(&descriptor, false, &[]).is_err());
+    }
+
+    #[test]
+    fn decode_numbering() {
+        let values = decode(COMPOSITE, true, &[0x02, 0xE9, 0x00]).unwrap();
+        assert_eq!(values, vec![(Usage::new(0x0C, 0xE9), 1)]);
+        // Without the report id the composite reports can't be told apart
+        assert_eq!(decode(COMPOSITE, false, &[0x02, 0xE9, 0x00]).unwrap(), vec![]);
+        // Devices that prefix their reports despite not declaring any report ids
+        assert_eq!(decode(MOUSE, true, &[0x00, 0b001, 0x01, 0xFF]).unwrap(), decode(MOUSE, false, &[0b001, 0x01, 0xFF]).unwrap());
+        assert!(decode(MOUSE, true, &[]).is_err());
+    }
+}
@@ -3,6 +3,7 @@ use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
 use std::panic::Location;
 
+use crate::backend;
 use crate::backend::BackendError;
 
 pub type HidResult<T> = Result<T, HidError>;
@@ -10,6 +11,12 @@ pub type HidResult<T> = Result<T, HidError>;
 #[derive(Debug)]
 pub enum ErrorSource {
     PlatformSpecific(BackendError),
+    /// The OS refused to open the device because the current user lacks the required permissions
+    ///
+    /// On Linux this usually means that no udev rule grants access to the hidraw node.
+    PermissionDenied(BackendError),
+    /// The device is not connected (anymore)
+    NotConnected,
     InvalidZeroSizeData,
     Custom(Cow<'static, str>)
 }
@@ -35,6 +42,27 @@ impl HidError {
             source: ErrorSource::InvalidZeroSizeData
         }
     }
+
+    /// Checks whether the device couldn't be opened due to missing permissions
+    pub fn is_permission_denied(&self) -> bool {
+        matches!(self.source, ErrorSource::PermissionDenied(_))
+    }
+
+    /// Checks whether the operation failed because the device is not connected
+    pub fn is_not_connected(&self) -> bool {
+        matches!(self.source, ErrorSource::NotConnected)
+    }
+
+    /// The error code reported by the OS, if this error originated from a failed OS call
+    ///
+    /// This is the `errno` value on Linux, the `HRESULT` on Windows (Win32 error codes are wrapped like `HRESULT_FROM_WIN32`)
+    /// and the `IOReturn` value on macOS.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match &self.source {
+            ErrorSource::PlatformSpecific(err) | ErrorSource::PermissionDenied(err) => backend::raw_os_error(err),
+            _ => None
+        }
+    }
 }
 
 impl Debug for HidError {
@@ -61,6 +89,29 @@ impl<T: Into<ErrorSource>> From<T> for HidError {
     }
 }
 
+/// A device that showed up during enumeration, but whose information couldn't be queried
+///
+/// This is returned by [DeviceInfo::enumerate_with_errors](crate::DeviceInfo::enumerate_with_errors).
+#[derive(Debug)]
+pub struct EnumerationError {
+    /// A platform specific description of the device (i.e. the sysfs path on Linux or the device interface path on Windows)
+    pub device: String,
+    /// The reason why the device information couldn't be queried
+    pub error: HidError
+}
+
+impl Display for EnumerationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to query device information for {}: {}", self.device, self.error)
+    }
+}
+
+impl Error for EnumerationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! ensure {
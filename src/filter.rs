@@ -0,0 +1,72 @@
+use crate::DeviceInfo;
+
+/// A set of criteria for finding a specific device
+///
+/// Every criterion is set through a named method, so the ids can't be accidentally mixed up.
+/// Only criteria that have been set are checked, so an empty filter matches every device.
+///
+/// ```
+/// # use async_hid::DeviceFilter;
+/// let filter = DeviceFilter::new()
+///     .vendor_id(0x046D)
+///     .product_id(0xC016)
+///     .usage_page(0x1)
+///     .usage_id(0x2);
+/// ```
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct DeviceFilter {
+    pub(crate) vendor_id: Option<u16>,
+    pub(crate) product_id: Option<u16>,
+    pub(crate) usage_page: Option<u16>,
+    pub(crate) usage_id: Option<u16>
+}
+
+impl DeviceFilter {
+    /// Creates a new filter that matches every device
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match devices with the given vendor id
+    pub fn vendor_id(mut self, vendor_id: u16) -> Self {
+        self.vendor_id = Some(vendor_id);
+        self
+    }
+
+    /// Only match devices with the given product id
+    pub fn product_id(mut self, product_id: u16) -> Self {
+        self.product_id = Some(product_id);
+        self
+    }
+
+    /// Only match devices with the given usage page
+    pub fn usage_page(mut self, usage_page: u16) -> Self {
+        self.usage_page = Some(usage_page);
+        self
+    }
+
+    /// Only match devices with the given usage id
+    pub fn usage_id(mut self, usage_id: u16) -> Self {
+        self.usage_id = Some(usage_id);
+        self
+    }
+
+    /// Checks if the given device satisfies all criteria of this filter
+    pub fn matches(&self, info: &DeviceInfo) -> bool {
+        self.vendor_id.is_none_or(|id| id == info.vendor_id)
+            && self.product_id.is_none_or(|id| id == info.product_id)
+            && self.usage_page.is_none_or(|page| page == info.usage_page)
+            && self.usage_id.is_none_or(|id| id == info.usage_id)
+    }
+}
+
+/// Checks if a device with the given ids can satisfy any of the filters while ignoring the usage criteria
+///
+/// This allows the backends to skip devices before querying their usages. An empty list of filters matches every device.
+#[cfg_attr(feature = "winrt", allow(dead_code))]
+pub(crate) fn any_matches_ids(filters: &[DeviceFilter], vendor_id: u16, product_id: u16) -> bool {
+    filters.is_empty()
+        || filters.iter().any(|filter| {
+            filter.vendor_id.is_none_or(|id| id == vendor_id) && filter.product_id.is_none_or(|id| id == product_id)
+        })
+}
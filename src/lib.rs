@@ -1,26 +1,58 @@
 #![doc = include_str!("../README.md")]
 
 mod backend;
+#[cfg(feature = "blocking")]
+mod blocking;
+mod descriptor;
 mod error;
+mod filter;
+mod options;
+mod report;
+mod shared;
+#[cfg(feature = "serde")]
+mod serialization;
 
-use std::fmt::{Debug, Formatter};
+use std::borrow::Borrow;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
 use std::future::Future;
 use std::hash::{Hash, Hasher};
+use std::num::NonZeroU8;
+use std::pin::pin;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
 
 use futures_core::Stream;
+use futures_lite::stream::unfold;
+use futures_lite::{FutureExt, StreamExt};
+use futures_timer::Delay;
 use static_assertions::assert_impl_all;
 use crate::backend::{BackendDevice, BackendDeviceId, BackendPrivateData};
-pub use crate::error::{ErrorSource, HidError, HidResult};
+pub use crate::descriptor::{ParsedDescriptor, ReportInfo};
+pub use crate::error::{EnumerationError, ErrorSource, HidError, HidResult};
+pub use crate::filter::DeviceFilter;
+pub use crate::options::OpenOptions;
+pub use crate::report::{Report, ReportType, Usage};
+pub use crate::shared::SharedDeviceReader;
 
 /// A struct containing basic information about a device
 ///
 /// This struct can be obtained by calling [DeviceInfo::enumerate] and upgraded into a usable [Device] by calling [DeviceInfo::open].
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DeviceInfo {
     /// OS specific identifier
     pub id: DeviceId,
     /// The human readable name
     pub name: String,
+    /// The name of the device's manufacturer, if it is known
+    ///
+    /// This is not available on Linux.
+    pub manufacturer: Option<String>,
     /// The HID product id assigned to this device
     pub product_id: u16,
     /// The HID vendor id of the device's manufacturer (i.e Logitech = 0x46D)
@@ -29,6 +61,16 @@ pub struct DeviceInfo {
     pub usage_id: u16,
     /// The HID usage page
     pub usage_page: u16,
+    /// The interval in which the host polls the device for new input reports
+    ///
+    /// This is currently only available for USB devices on Linux and macOS.
+    pub polling_interval: Option<Duration>,
+    /// The bus over which the device is connected
+    pub bus_type: BusType,
+    /// The release number of the device (the `bcdDevice` field of USB devices)
+    ///
+    /// This is usually the firmware version in binary-coded decimal. On Linux, it is only available for USB devices and `0` otherwise.
+    pub release_number: u16,
 
     pub(crate) private_data: BackendPrivateData,
 }
@@ -39,33 +81,327 @@ impl DeviceInfo {
     /// If this library fails to retrieve the [DeviceInfo] of a device it will be automatically excluded.
     /// Register a `log` compatible logger at `trace` level for more information about the discarded devices.
     pub fn enumerate() -> impl Future<Output = HidResult<impl Stream<Item = DeviceInfo> + Unpin + Send>> {
-        backend::enumerate()
+        Self::enumerate_with_options(EnumerateOptions::default())
+    }
+
+    /// Enumerates all **accessible** HID devices while only querying the information requested by `options`
+    ///
+    /// See [EnumerateOptions] for more information.
+    pub async fn enumerate_with_options(options: EnumerateOptions) -> HidResult<impl Stream<Item = DeviceInfo> + Unpin + Send> {
+        Ok(backend::enumerate(options, &[]).await?.filter_map(skip_failed))
+    }
+
+    /// Enumerates all HID devices, including the ones whose information couldn't be queried
+    ///
+    /// [DeviceInfo::enumerate] silently skips these devices, which makes it hard to tell why a device is missing.
+    /// This yields an [EnumerationError] for each of them instead, which describes the device and the reason (i.e. missing permissions).
+    pub fn enumerate_with_errors() -> impl Future<Output = HidResult<impl Stream<Item = Result<DeviceInfo, EnumerationError>> + Unpin + Send>> {
+        backend::enumerate(EnumerateOptions::default(), &[])
+    }
+
+    /// Enumerates all **accessible** HID devices that match at least one of the given filters
+    ///
+    /// This is equivalent to filtering the result of [DeviceInfo::enumerate] with [DeviceInfo::matches_filter], but skips the expensive parts of
+    /// the enumeration (like parsing the report descriptor or opening the device) for devices whose vendor and product id already rule them out.
+    /// An empty slice matches every device.
+    pub fn enumerate_filtered(filters: &[DeviceFilter]) -> impl Future<Output = HidResult<impl Stream<Item = DeviceInfo> + Unpin + Send>> {
+        let filters = filters.to_vec();
+        async move {
+            let devices = backend::enumerate(EnumerateOptions::default(), &filters)
+                .await?
+                .filter_map(skip_failed);
+            Ok(devices.filter(move |info| filters.is_empty() || filters.iter().any(|filter| info.matches_filter(filter))))
+        }
+    }
+
+    /// Discards the information cached by enumerations with [EnumerateOptions::use_cache]
+    ///
+    /// The next enumeration queries every device again.
+    pub fn clear_enumeration_cache() {
+        backend::clear_enumeration_cache()
+    }
+
+    /// Enumerates all **accessible** HID devices and collects them into a [Vec]
+    pub async fn enumerate_all() -> HidResult<Vec<DeviceInfo>> {
+        Ok(Self::enumerate().await?.collect().await)
+    }
+
+    /// Returns the first **accessible** HID device that satisfies `predicate`
+    ///
+    /// The enumeration stops as soon as a matching device is found, so the remaining devices are never queried.
+    /// The `win32` backend is the exception, as it queries all devices before returning the first one.
+    pub async fn find(predicate: impl FnMut(&DeviceInfo) -> bool) -> HidResult<Option<DeviceInfo>> {
+        Ok(Self::enumerate().await?.find(predicate).await)
+    }
+
+    /// Enumerates all **accessible** HID devices and sorts them by their [DeviceId]
+    ///
+    /// The order of [DeviceInfo::enumerate] depends on the OS and can change between runs, so this is useful when multiple identical devices are attached.
+    /// Sorting requires the complete list of devices, so unlike [DeviceInfo::enumerate] this only returns once all devices have been enumerated.
+    pub async fn enumerate_sorted() -> HidResult<Vec<DeviceInfo>> {
+        let mut devices: Vec<DeviceInfo> = Self::enumerate().await?.collect().await;
+        // The sort is stable, so the different usages of the same device keep their order
+        devices.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(devices)
+    }
+
+    /// Enumerates all **accessible** HID devices and groups them by the physical device they belong to
+    ///
+    /// A single physical device can expose multiple HID interfaces (i.e. a keyboard with media keys), which are listed as separate [DeviceInfo]s by [DeviceInfo::enumerate].
+    /// Interfaces are grouped by their serial number if it is available and by their parent device otherwise
+    /// (the USB device on Linux, the container id on Windows and the location id on macOS).
+    /// Interfaces for which neither is known end up in a group of their own.
+    pub async fn enumerate_grouped() -> HidResult<Vec<PhysicalDevice>> {
+        let mut devices: Vec<PhysicalDevice> = Vec::new();
+        let mut groups: HashMap<_, usize> = HashMap::new();
+        let mut interfaces = Self::enumerate().await?;
+        while let Some(info) = interfaces.next().await {
+            let serial_number = info.serial_number().filter(|s| !s.is_empty()).map(str::to_string);
+            let key = match (&serial_number, info.physical_device_id()) {
+                (Some(serial_number), _) => GroupKey::SerialNumber(serial_number.clone()),
+                (None, Some(parent)) => GroupKey::Parent(parent.to_string()),
+                (None, None) => GroupKey::Device(info.id.clone())
+            };
+            match groups.entry((info.vendor_id, info.product_id, key)) {
+                Entry::Occupied(entry) => devices[*entry.get()].interfaces.push(info),
+                Entry::Vacant(entry) => {
+                    entry.insert(devices.len());
+                    devices.push(PhysicalDevice {
+                        vendor_id: info.vendor_id,
+                        product_id: info.product_id,
+                        serial_number,
+                        interfaces: vec![info]
+                    });
+                }
+            }
+        }
+        Ok(devices)
+    }
+
+    /// Enumerates all **accessible** HID devices with one entry per [DeviceId] instead of one per top-level collection
+    ///
+    /// [DeviceInfo::enumerate] lists a device with multiple top-level collections once for every collection, with only the usage being different.
+    /// This merges these entries and collects their usages in enumeration order. The [DeviceInfo] of each entry is the one of the first collection.
+    /// The `win32` backend already exposes every collection under its own [DeviceId], so nothing gets merged there.
+    pub async fn enumerate_collapsed() -> HidResult<Vec<CollapsedDeviceInfo>> {
+        let mut devices: Vec<CollapsedDeviceInfo> = Vec::new();
+        let mut indices: HashMap<DeviceId, usize> = HashMap::new();
+        let mut collections = Self::enumerate().await?;
+        while let Some(info) = collections.next().await {
+            let usage = Usage::new(info.usage_page, info.usage_id);
+            match indices.entry(info.id.clone()) {
+                Entry::Occupied(entry) => devices[*entry.get()].usages.push(usage),
+                Entry::Vacant(entry) => {
+                    entry.insert(devices.len());
+                    devices.push(CollapsedDeviceInfo { info, usages: vec![usage] });
+                }
+            }
+        }
+        Ok(devices)
+    }
+
+    /// Looks up the information of the device with the given id
+    ///
+    /// This is useful for restoring a device that was selected in a previous run, as [DeviceId] can be converted to and from a string.
+    /// Fails with [ErrorSource::NotConnected] if no accessible device with this id is connected.
+    ///
+    /// The device is queried directly, without enumerating every other device. On Linux and macOS a single id covers all
+    /// top-level collections of the device, in which case the info describes the primary one (the first collection of the
+    /// report descriptor on Linux and the primary usage on macOS).
+    pub async fn from_id(id: &DeviceId) -> HidResult<DeviceInfo> {
+        backend::get_device_info(&id.0)
+            .await
+            .map_err(|err| match backend::is_connected(&id.0) {
+                true => err,
+                false => ErrorSource::NotConnected.into()
+            })
     }
 
     /// Opens the associated device in the requested [AccessMode]
+    ///
+    /// The same device can be opened more than once, e.g. once with [AccessMode::Read] for a reader task and once with
+    /// [AccessMode::Write] for a writer task. Every call creates its own OS handle, so the resulting devices are fully independent
+    /// and can be dropped in any order. This works on Linux, macOS and with the `win32` backend as long as no handle was opened
+    /// with [ReadConfig::exclusive]. WinRT only allows a single handle with write access per device, so a second
+    /// [AccessMode::Write] or [AccessMode::ReadWrite] handle fails, while any number of [AccessMode::Read] handles are allowed.
     pub async fn open(&self, mode: AccessMode) -> HidResult<Device> {
-        let dev = backend::open(&self.id.0, mode).await?;
+        self.open_with_config(mode, ReadConfig::default()).await
+    }
+
+    /// Opens the associated device in the requested [AccessMode] and configures the input report handling according to `config`
+    ///
+    /// A [DeviceInfo] can be stored (i.e. with the `serde` feature) and opened again later without enumerating the devices first.
+    /// If the device is no longer connected, this fails with [ErrorSource::NotConnected].
+    pub async fn open_with_config(&self, mode: AccessMode, config: ReadConfig) -> HidResult<Device> {
+        let dev = match backend::open(&self.id.0, mode, &config).await {
+            Ok(dev) => dev,
+            Err(_) if !self.is_still_present().await => return Err(ErrorSource::NotConnected.into()),
+            Err(err) => return Err(err)
+        };
+        let report_filter = match config.filter_collection {
+            true => dev
+                .collection_input_report_ids(self.usage_page, self.usage_id)?
+                // Without report ids there is no way to tell the collections apart
+                .filter(|ids| !ids.is_empty() && !ids.contains(&0))
+                // The descriptor declares report ids at this point, but they might be overridden by `report_id_mode`
+                .filter(|_| config.numbered_reports(|| true)),
+            false => None
+        };
         Ok(Device {
             inner: dev,
             info: self.clone(),
-            mode
+            mode,
+            discard_stale_reports: AtomicBool::new(config.lazy),
+            report_filter,
+            solicit_on_idle: config.solicit_on_idle,
+            serial_number: OnceLock::new()
+        })
+    }
+
+    /// Opens the associated device with the settings collected in `options`
+    ///
+    /// This is equivalent to [DeviceInfo::open_with_config], but keeps all settings in a single builder. See [OpenOptions] for more information.
+    pub async fn open_with(&self, options: OpenOptions) -> HidResult<Device> {
+        self.open_with_config(options.access_mode(), options.read_config()).await
+    }
+
+    /// Opens the associated device, retrying up to `attempts` times if opening fails
+    ///
+    /// This is useful when opening a device that was just plugged in, as the OS might not have finished initializing it yet.
+    /// The delay between attempts starts at `backoff` and doubles after every failed attempt.
+    ///
+    /// Most platforms don't report a distinct error for devices that are still initializing,
+    /// so **every** error is considered retryable. The error of the last attempt is returned if all attempts fail.
+    pub async fn open_with_retry(&self, mode: AccessMode, attempts: usize, backoff: Duration) -> HidResult<Device> {
+        let mut delay = backoff;
+        let mut attempt = 1;
+        loop {
+            match self.open(mode).await {
+                Ok(device) => return Ok(device),
+                Err(err) if attempt < attempts => {
+                    log::debug!("Failed to open device (attempt {attempt}/{attempts}), retrying in {delay:?}\n\tbecause {err:?}");
+                    Delay::new(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(err) => return Err(err)
+            }
+        }
+    }
+
+    /// Opens the associated device and looks for it again if it briefly disconnected, retrying up to `attempts` times
+    ///
+    /// Some devices (i.e. Bluetooth controllers) drop off the bus and immediately reconnect, often with a different [DeviceId].
+    /// If opening fails with [ErrorSource::NotConnected], this waits and enumerates the devices again to find the same device.
+    /// The device is matched by its [DeviceInfo::stable_key], so devices without a serial number are only found again under the same [DeviceId].
+    /// The delay between attempts starts at `backoff` and doubles after every failed attempt.
+    ///
+    /// Unlike [DeviceInfo::open_with_retry], every other error is returned immediately.
+    pub async fn open_resilient(&self, mode: AccessMode, attempts: usize, backoff: Duration) -> HidResult<Device> {
+        let mut info = self.clone();
+        let mut delay = backoff;
+        let mut attempt = 1;
+        loop {
+            match info.open(mode).await {
+                Err(err) if err.is_not_connected() && attempt < attempts => {
+                    log::debug!("Device is not connected (attempt {attempt}/{attempts}), looking for it again in {delay:?}");
+                    Delay::new(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                    if let Some(found) = Self::find(|other| self.is_same_device(other)).await? {
+                        info = found;
+                    }
+                }
+                result => return result
+            }
+        }
+    }
+
+    fn is_same_device(&self, other: &DeviceInfo) -> bool {
+        self.stable_key() == other.stable_key()
+    }
+
+    /// An identity of this device that stays the same across reconnects and reboots if the device has a serial number
+    ///
+    /// [DeviceId]s are assigned by the OS and can change whenever the device reconnects (on macOS even for the same port).
+    /// See [StableKey] for how the key is built.
+    pub fn stable_key(&self) -> StableKey {
+        match self.serial_number().filter(|s| !s.is_empty()) {
+            Some(serial_number) => StableKey::SerialNumber {
+                vendor_id: self.vendor_id,
+                product_id: self.product_id,
+                serial_number: serial_number.to_string(),
+                usage: Usage::new(self.usage_page, self.usage_id)
+            },
+            None => StableKey::Id(self.id.clone())
+        }
+    }
+
+    /// Checks whether the device could currently be opened for reading and writing
+    ///
+    /// This briefly opens and closes the device, so it doesn't interfere with handles that are opened later.
+    /// Useful for telling the user why a device can't be used (i.e. because another application grabbed it) before trying to open it.
+    /// On Linux, hidraw nodes can't be opened exclusively, so [AccessState::BusyExclusive] is only reported if the driver refuses the open.
+    pub fn access_state(&self) -> HidResult<AccessState> {
+        backend::access_state(&self.id.0).map_err(|err| match backend::is_connected(&self.id.0) {
+            true => err,
+            false => ErrorSource::NotConnected.into()
         })
     }
 
+    /// Checks whether the id of this info still refers to the same device
+    ///
+    /// Linux reuses the numbers of hidraw nodes, so the node of an unplugged device can belong to a different device later on.
+    async fn is_still_present(&self) -> bool {
+        backend::is_connected(&self.id.0)
+            && backend::get_device_info(&self.id.0)
+                .await
+                .is_ok_and(|current| {
+                    current.vendor_id == self.vendor_id
+                        && current.product_id == self.product_id
+                        // The serial number is only known if it was fetched during enumeration
+                        && self.serial_number().is_none_or(|serial| current.serial_number() == Some(serial))
+                })
+    }
+
     /// Convenience method for easily finding a specific device
+    #[deprecated(note = "the positional arguments are easy to mix up, use `DeviceInfo::matches_filter` instead")]
     pub fn matches(&self, usage_page: u16, usage_id: u16, vendor_id: u16, product_id: u16) -> bool {
-        self.usage_page == usage_page && self.usage_id == usage_id && self.vendor_id == vendor_id && self.product_id == product_id
+        let filter = DeviceFilter::new()
+            .usage_page(usage_page)
+            .usage_id(usage_id)
+            .vendor_id(vendor_id)
+            .product_id(product_id);
+        self.matches_filter(&filter)
     }
+
+    /// Convenience method for easily finding a specific device
+    ///
+    /// See [DeviceFilter] for more information.
+    pub fn matches_filter(&self, filter: &DeviceFilter) -> bool {
+        filter.matches(self)
+    }
+}
+
+/// Drops devices whose information couldn't be queried from the lossy enumeration functions
+fn skip_failed(result: Result<DeviceInfo, EnumerationError>) -> Option<DeviceInfo> {
+    result
+        .map_err(|err| log::trace!("Failed to query device information for {}\n\tbecause {:?}", err.device, err.error))
+        .ok()
 }
 
 impl Hash for DeviceInfo {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.id.hash(state);
         self.name.hash(state);
+        self.manufacturer.hash(state);
         self.product_id.hash(state);
         self.vendor_id.hash(state);
         self.usage_id.hash(state);
         self.usage_page.hash(state);
+        self.polling_interval.hash(state);
+        self.bus_type.hash(state);
+        self.release_number.hash(state);
     }
 }
 
@@ -73,33 +409,285 @@ impl PartialEq for DeviceInfo {
     fn eq(&self, other: &Self) -> bool {
         self.id == other.id
             && self.name == other.name
+            && self.manufacturer == other.manufacturer
             && self.product_id == other.product_id
             && self.vendor_id == other.vendor_id
             && self.usage_id == other.usage_id
             && self.usage_page == other.usage_page
+            && self.polling_interval == other.polling_interval
+            && self.bus_type == other.bus_type
+            && self.release_number == other.release_number
     }
 }
 
 impl Eq for DeviceInfo {}
 
+/// The bus over which a device is connected
+///
+/// The same device can use different report layouts depending on how it is connected (i.e. USB and Bluetooth).
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BusType {
+    #[default]
+    Unknown,
+    Usb,
+    Bluetooth,
+    I2c,
+    Spi
+}
+
+/// The platform API used to talk to the devices
+///
+/// The backend is chosen at compile time based on the target OS and the enabled features.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum BackendType {
+    Hidraw,
+    Win32,
+    WinRt,
+    IoHidManager
+}
+
+impl BackendType {
+    /// The backend this crate was compiled with
+    pub const fn active() -> Self {
+        backend::BACKEND_TYPE
+    }
+}
+
+/// A physical device together with all the HID interfaces it exposes
+///
+/// This struct can be obtained by calling [DeviceInfo::enumerate_grouped].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PhysicalDevice {
+    /// The HID vendor id shared by all interfaces
+    pub vendor_id: u16,
+    /// The HID product id shared by all interfaces
+    pub product_id: u16,
+    /// The serial number shared by all interfaces, if the device has one
+    pub serial_number: Option<String>,
+    /// The individual interfaces of this device in enumeration order
+    pub interfaces: Vec<DeviceInfo>
+}
+
+/// An identity of a device that can be persisted and compared across sessions
+///
+/// This struct can be obtained by calling [DeviceInfo::stable_key].
+/// Devices with a serial number are identified by their vendor id, product id, serial number and usage, which stays the same across
+/// reconnects and reboots. All other devices fall back to their [DeviceId], which is only stable as long as the device stays connected.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StableKey {
+    SerialNumber {
+        vendor_id: u16,
+        product_id: u16,
+        serial_number: String,
+        usage: Usage
+    },
+    Id(DeviceId)
+}
+
+/// A device together with the usages of all its top-level collections
+///
+/// This struct can be obtained by calling [DeviceInfo::enumerate_collapsed].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CollapsedDeviceInfo {
+    /// The information of the first collection
+    pub info: DeviceInfo,
+    /// The usages of all top-level collections in enumeration order
+    pub usages: Vec<Usage>
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+enum GroupKey {
+    SerialNumber(String),
+    Parent(String),
+    Device(DeviceId)
+}
+
 pub trait SerialNumberExt {
     fn serial_number(&self) -> Option<&str>;
 }
 
+/// The result of [Device::read_input_report_full]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ReadOutcome {
+    /// The number of bytes that were copied into the buffer
+    pub copied: usize,
+    /// The full length of the report
+    ///
+    /// Like `copied`, this only includes the leading report id if the device uses numbered reports.
+    pub report_len: usize,
+    /// The id of the report or `None` if the device doesn't use numbered reports
+    pub report_id: Option<NonZeroU8>
+}
+
+impl ReadOutcome {
+    /// Whether the report didn't fit into the buffer and got truncated
+    pub fn is_truncated(&self) -> bool {
+        self.copied < self.report_len
+    }
+
+    /// Turns the buffer that was passed to [Device::read_input_report_full] into a [Report]
+    fn to_report(self, buf: &[u8]) -> Report {
+        let data = &buf[..self.copied];
+        match self.report_id {
+            Some(_) => Report::from_bytes(data),
+            None => Report::new(0, data)
+        }
+    }
+}
+
 /// A struct representing an opened device
 ///
 /// Dropping this struct will close the associated device
+///
+/// All methods only require `&self`, so a device can be wrapped in an [Arc](std::sync::Arc) and shared between a reading and a writing task.
+/// Reads and writes use independent buffers, but only one read and one write can be in flight at the same time.
 pub struct Device {
     inner: BackendDevice,
     info: DeviceInfo,
-    mode: AccessMode
+    mode: AccessMode,
+    discard_stale_reports: AtomicBool,
+    report_filter: Option<Vec<u8>>,
+    solicit_on_idle: Option<Duration>,
+    serial_number: OnceLock<Option<String>>
 }
 
 impl Device {
+    /// Checks whether the device is still connected without performing any I/O on the open handle
+    ///
+    /// This doesn't consume or discard any pending input reports. A device that gets unplugged right after this check
+    /// is of course not detected, so reads and writes still have to handle errors.
+    pub fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    /// A human-readable description of where the device is physically connected, meant for logging and diagnostics
+    ///
+    /// Unlike [DeviceId], this reflects the port the device is plugged into, so it can be used to tell identical devices apart.
+    /// This is the sysfs path of the device on Linux, the path in the `IOService` plane on macOS and the location path of the
+    /// device (or its instance id if it has no location) on Windows. The format is platform specific and should not be parsed.
+    pub fn location(&self) -> Option<String> {
+        backend::location(&self.info.id.0)
+    }
+
+    /// The backend that opened this device
+    pub fn backend_type(&self) -> BackendType {
+        BackendType::active()
+    }
+
     /// Read a input report from this device
+    ///
+    /// The report is normalized to be the same on every platform: The first byte is the report id if the device uses numbered reports.
+    /// Otherwise, the buffer only contains the payload. See [Device::read_input_report_raw] for the unmodified report.
     pub fn read_input_report<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = HidResult<usize>> + Send + 'a {
+        let read = self.read_input_report_full(buf);
+        async move { read.await.map(|outcome| outcome.copied) }
+    }
+
+    /// Read a input report from this device, giving up after `timeout`
+    ///
+    /// Works like [Device::read_input_report], but returns `Ok(None)` if no report arrived in time.
+    /// Timing out never loses a report: Reports that arrive afterward are returned by the next read.
+    pub async fn read_input_report_timeout(&self, buf: &mut [u8], timeout: Duration) -> HidResult<Option<usize>> {
+        let read = async { Some(self.read_input_report(buf).await) };
+        let timeout = async {
+            Delay::new(timeout).await;
+            None
+        };
+        read.or(timeout).await.transpose()
+    }
+
+    /// Read a input report from this device if one is available right now
+    ///
+    /// Works like [Device::read_input_report], but returns `Ok(None)` instead of waiting if no report is available.
+    /// This is useful for polling the device from a synchronous loop (i.e. the render loop of an immediate mode GUI).
+    /// With `tokio`, reports might only become visible after the runtime had a chance to process the readiness events of the device.
+    pub fn try_read_input_report(&self, buf: &mut [u8]) -> HidResult<Option<usize>> {
+        // Every backend keeps a pending read around, so abandoning the future after a single poll never loses a report
+        let read = pin!(self.read_input_report(buf));
+        match read.poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(result) => result.map(Some),
+            Poll::Pending => Ok(None)
+        }
+    }
+
+    /// Read a input report from this device exactly as the OS delivered it
+    ///
+    /// Unlike [Device::read_input_report], the report id is neither stripped nor inserted, so the format depends on the platform.
+    /// On Windows, reports of devices that don't use numbered reports start with a `0` byte, while the other platforms only deliver the payload.
+    /// Reports are also not filtered by [ReadConfig::filter_collection] and [ReadConfig::solicit_on_idle] is not applied.
+    pub fn read_input_report_raw<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = HidResult<usize>> + Send + 'a {
+        debug_assert!(self.mode.readable());
+        async move {
+            if self.discard_stale_reports.swap(false, Ordering::Relaxed) {
+                self.inner.clear_input_reports()?;
+            }
+            self.inner.read_input_report_raw(buf).await
+        }
+    }
+
+    /// Read a input report from this device and report whether it fit into `buf`
+    ///
+    /// This works like [Device::read_input_report], but additionally returns the full length and the id of the report.
+    /// Reports that are larger than `buf` are truncated, which can be detected with [ReadOutcome::is_truncated].
+    pub fn read_input_report_full<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = HidResult<ReadOutcome>> + Send + 'a {
         debug_assert!(self.mode.readable());
-        self.inner.read_input_report(buf)
+        async move {
+            if self.discard_stale_reports.swap(false, Ordering::Relaxed) {
+                self.inner.clear_input_reports()?;
+            }
+            loop {
+                let outcome = match self.solicit_on_idle {
+                    Some(idle) => {
+                        let read = async { Some(self.inner.read_input_report(buf).await) };
+                        let timeout = async {
+                            Delay::new(idle).await;
+                            None
+                        };
+                        match read.or(timeout).await {
+                            Some(result) => result?,
+                            None => {
+                                log::trace!("[{}] No input report received within {idle:?}, requesting one", self.info.id);
+                                self.solicit_input_report(buf).await?
+                            }
+                        }
+                    }
+                    None => self.inner.read_input_report(buf).await?
+                };
+                match &self.report_filter {
+                    Some(ids) if outcome.copied > 0 && !ids.contains(&buf[0]) => {
+                        log::trace!("[{}] Skipping input report with id {} from another collection", self.info.id, buf[0])
+                    }
+                    _ => return Ok(outcome)
+                }
+            }
+        }
+    }
+
+    /// Request an input report and return it in the same format as [Device::read_input_report]
+    async fn solicit_input_report(&self, buf: &mut [u8]) -> HidResult<ReadOutcome> {
+        ensure!(!buf.is_empty(), HidError::zero_sized_data());
+        buf[0] = match &self.report_filter {
+            Some(ids) => ids[0],
+            None => self
+                .inner
+                .input_report_ids()?
+                .first()
+                .map_or(0, |(id, _)| *id)
+        };
+        let mut size = self.inner.get_input_report(buf).await?;
+        let report_id = NonZeroU8::new(buf[0]);
+        // Unlike GET_REPORT, regular reads don't include the report id of unnumbered reports
+        if report_id.is_none() && size > 0 {
+            buf.copy_within(1..size, 0);
+            size -= 1;
+        }
+        Ok(ReadOutcome {
+            copied: size,
+            report_len: size,
+            report_id
+        })
     }
 
     /// Write an output report to this device
@@ -108,14 +696,333 @@ impl Device {
         self.inner.write_output_report(buf)
     }
 
+    /// Write multiple output reports to this device in order
+    ///
+    /// Every report has the same format as for [Device::write_output_report]. This is more efficient than writing them one by one,
+    /// as WinRT submits all reports at once and the `win32` backend reuses its write buffer without letting other writes in between.
+    /// Writing stops at the first error, in which case the previous reports have already been sent.
+    pub async fn write_output_reports(&self, reports: &[&[u8]]) -> HidResult<()> {
+        debug_assert!(self.mode.writeable());
+        self.inner.write_output_reports(reports).await
+    }
+
+    /// Write an output report to this device, giving up after `timeout`
+    ///
+    /// Returns `false` if the write didn't complete in time, in which case the pending write is canceled before returning.
+    /// On macOS writes are synchronous and the timeout is ignored; IOKit aborts writes to unresponsive devices on its own.
+    pub async fn write_output_report_timeout(&self, buf: &[u8], timeout: Duration) -> HidResult<bool> {
+        debug_assert!(self.mode.writeable());
+        self.inner.write_output_report_timeout(buf, timeout).await
+    }
+
+    /// Write an output report to this device
+    ///
+    /// This is a convenience wrapper around [Device::write_output_report] that takes care of prepending the report id.
+    /// See [Report::validate_against] for checking the report beforehand.
+    pub async fn write_report(&self, report: &Report) -> HidResult<()> {
+        self.write_output_report(&report.to_bytes()).await
+    }
+
+    /// Read an input report from this device and decode it into the values of its individual controls
+    ///
+    /// The layout of the report is taken from the report descriptor on Linux and macOS and from the HID class driver on Windows.
+    /// Variable controls (like axes) are reported with their current value. Buttons and array controls (like the keys of a keyboard)
+    /// are reported with a value of `1` while they are active. Inactive buttons are reported with a value of `0` on Linux and macOS,
+    /// but are omitted on Windows.
+    /// Reports are decoded with the same numbering they were read with, so [ReadConfig::report_id_mode] applies here as well.
+    pub async fn read_decoded(&self) -> HidResult<Vec<(Usage, i64)>> {
+        let mut buf = vec![0u8; self.inner.max_input_report_len()?.max(1)];
+        let size = self.read_input_report(&mut buf).await?;
+        self.inner.decode_input_report(&buf[..size])
+    }
+
+    /// Read the next input report from this device into a newly allocated [Report]
+    ///
+    /// Unlike [Device::read_input_report] the buffer is automatically sized and the report id is always kept separate from the payload.
+    /// Reports from devices that don't use numbered reports are returned with the report id `0`.
+    pub async fn read_report(&self) -> HidResult<Report> {
+        let mut buf = vec![0u8; self.inner.max_input_report_len()?.max(1)];
+        let outcome = self.read_input_report_full(&mut buf).await?;
+        Ok(outcome.to_report(&buf))
+    }
+
+    /// Consumes this device and turns it into a stream of decoded input reports
+    ///
+    /// See [Device::read_decoded] for the format of the individual items.
+    /// Reports that can't be decoded are yielded as errors without ending the stream.
+    /// If reading from the device fails (e.g. because it got disconnected), the error is yielded and the stream ends.
+    pub fn input_events(self) -> impl Stream<Item = HidResult<Vec<(Usage, i64)>>> + Send {
+        unfold(Some((self, Vec::new())), |state| async move {
+            let (device, mut buf) = state?;
+            if buf.is_empty() {
+                match device.inner.max_input_report_len() {
+                    Ok(len) => buf.resize(len.max(1), 0),
+                    Err(err) => return Some((Err(err), None))
+                }
+            }
+            match device.read_input_report(&mut buf).await {
+                Ok(size) => Some((device.inner.decode_input_report(&buf[..size]), Some((device, buf)))),
+                Err(err) => Some((Err(err), None))
+            }
+        })
+    }
+
+    /// Turns this device into an endless stream of input reports
+    ///
+    /// Every item is read like with [Device::read_report]. The stream ends once the device got disconnected.
+    /// Any other error is yielded and ends the stream as well.
+    pub fn report_stream(&self) -> impl Stream<Item = HidResult<Report>> + Send + '_ {
+        report_stream(self)
+    }
+
+    /// Consumes this device and turns it into an endless stream of input reports
+    ///
+    /// Works like [Device::report_stream], but the device is closed when the stream is dropped.
+    pub fn into_report_stream(self) -> impl Stream<Item = HidResult<Report>> + Send {
+        report_stream(self)
+    }
+
+    /// Close this device and report any errors that occur while doing so
+    ///
+    /// Dropping a device does the same, but can only log errors. Pending reads and writes are canceled and, on macOS and WinRT,
+    /// the input report callback is unregistered before the device itself is closed.
+    pub fn close(self) -> HidResult<()> {
+        self.inner.close()
+    }
+
+    /// Consumes this device and turns it into a read handle that can be cloned to let multiple consumers see every input report
+    ///
+    /// See [SharedDeviceReader] for details.
+    pub fn into_shared_reader(self) -> SharedDeviceReader {
+        SharedDeviceReader::new(self)
+    }
+
+    /// Consumes this device and turns it into a stream that yields the next `n` input reports and then ends
+    ///
+    /// Reports from devices that don't use numbered reports are returned with the report id `0`.
+    /// A new read is only started when the next report is requested, so no read is in flight once the last report was yielded.
+    /// The device is closed right after that. If reading fails (e.g. because the device got disconnected), the error is yielded and
+    /// the stream ends early.
+    pub fn take_reports(self, n: usize) -> impl Stream<Item = HidResult<Report>> + Send {
+        unfold(Some((self, Vec::new(), n)), |state| async move {
+            let (device, mut buf, remaining) = state.filter(|(_, _, remaining)| *remaining > 0)?;
+            if buf.is_empty() {
+                match device.inner.max_input_report_len() {
+                    Ok(len) => buf.resize(len.max(1), 0),
+                    Err(err) => return Some((Err(err), None))
+                }
+            }
+            match device.read_input_report_full(&mut buf).await {
+                Ok(outcome) => {
+                    let report = outcome.to_report(&buf);
+                    // Dropping the state right away closes the device after the last report
+                    let next = (remaining > 1).then_some((device, buf, remaining - 1));
+                    Some((Ok(report), next))
+                }
+                Err(err) => Some((Err(err), None))
+            }
+        })
+    }
+
+    /// Request an input report from this device
+    ///
+    /// Unlike [Device::read_input_report], this doesn't wait for the device to send a report, but instead asks the device
+    /// for the current value of the report (i.e. the `GET_REPORT` request).
+    /// The first byte of `buf` must contain the id of the requested report (or `0` if the device doesn't use numbered reports).
+    /// The returned length includes this report id.
+    pub fn get_input_report<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = HidResult<usize>> + Send + 'a {
+        self.inner.get_input_report(buf)
+    }
+
+    /// Request the input report with the given id from this device
+    ///
+    /// Unlike [Device::get_input_report] the buffer is automatically sized based on the length of the requested report.
+    pub async fn get_input_report_with_id(&self, id: u8) -> HidResult<Report> {
+        let mut buf = vec![0u8; self.inner.input_report_len(id)?.max(1)];
+        buf[0] = id;
+        let size = self.get_input_report(&mut buf).await?;
+        Ok(Report::from_bytes(&buf[..size]))
+    }
+
+    /// Read a feature report from this device
+    ///
+    /// The first byte of `buf` must contain the id of the requested report (or `0` if the device doesn't use numbered reports).
+    /// The returned length includes this report id.
+    pub fn read_feature_report<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = HidResult<usize>> + Send + 'a {
+        self.inner.read_feature_report(buf)
+    }
+
+    /// Read a feature report from this device, giving up after `timeout`
+    ///
+    /// Works like [Device::read_feature_report], but returns `Ok(None)` if the device didn't answer in time.
+    /// On macOS, the timeout is enforced by IOKit for devices opened with read access.
+    /// On Linux and with the `win32` backend the request is synchronous and the timeout is ignored; the OS aborts requests to unresponsive devices after a few seconds.
+    pub async fn read_feature_report_timeout(&self, buf: &mut [u8], timeout: Duration) -> HidResult<Option<usize>> {
+        self.inner.read_feature_report_timeout(buf, timeout).await
+    }
+
+    /// Read the feature report with the given id from this device
+    ///
+    /// Unlike [Device::read_feature_report] the buffer is automatically sized based on the length of the requested report.
+    pub async fn feature_report(&self, id: u8) -> HidResult<Report> {
+        let buf = self.read_feature_report_auto(id).await?;
+        Ok(Report::from_bytes(&buf))
+    }
+
+    /// Read the feature report with the given id from this device into a newly allocated buffer
+    ///
+    /// The buffer is sized based on the length of the requested report and trimmed to the number of bytes actually received.
+    /// Like with [Device::read_feature_report], the first byte of the result is the report id.
+    pub async fn read_feature_report_auto(&self, id: u8) -> HidResult<Vec<u8>> {
+        let len = self.inner.feature_report_len(id)?;
+        self.read_feature_report_sized(id, len).await
+    }
+
+    /// Read the feature report with the given id from this device into a newly allocated buffer of `len` bytes
+    ///
+    /// Works like [Device::read_feature_report_auto], but uses the given length (including the report id) instead of looking it up.
+    /// This is useful for devices whose report descriptor doesn't match the reports they actually send.
+    pub async fn read_feature_report_sized(&self, id: u8, len: usize) -> HidResult<Vec<u8>> {
+        let mut buf = vec![0u8; len.max(1)];
+        buf[0] = id;
+        let size = self.read_feature_report(&mut buf).await?;
+        buf.truncate(size);
+        Ok(buf)
+    }
+
+    /// Write a feature report to this device
+    ///
+    /// The first byte of `buf` must contain the id of the report (or `0` if the device doesn't use numbered reports).
+    /// On Windows, the report is automatically padded with zeros to the length expected by the device.
+    pub fn write_feature_report<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = HidResult<()>> + Send + 'a {
+        self.inner.write_feature_report(buf)
+    }
+
+    /// Request the current state of an output report from the device
+    ///
+    /// Some devices only expose certain state (i.e. LEDs) through output reports and answer a `GET_REPORT` request for them.
+    /// Works like [Device::get_input_report], so the first byte of `buf` must contain the id of the requested report.
+    /// This is only supported on macOS, as neither hidraw nor the Windows HID class driver can request output reports.
+    pub async fn get_output_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        self.inner.get_output_report(buf).await
+    }
+
+    /// Read the USB string descriptor with the given index
+    ///
+    /// Besides the well-known strings (like the serial number), some devices store additional information in custom string descriptors.
+    /// Returns `Ok(None)` if the string is empty. On Linux, this requires the same access as [Device::control_transfer].
+    /// This is not supported on macOS.
+    pub async fn indexed_string(&self, index: u32) -> HidResult<Option<String>> {
+        self.inner.indexed_string(index).await
+    }
+
+    /// Send a raw control transfer to the USB device this interface belongs to
+    ///
+    /// This is an escape hatch for devices that expect vendor specific requests besides `GET_REPORT` and `SET_REPORT`.
+    /// The direction of the transfer is determined by the highest bit of `request_type`: for device-to-host requests `data`
+    /// receives the response, otherwise its content is sent to the device. Returns the number of bytes that were transferred.
+    ///
+    /// This is currently only supported for USB devices on Linux and requires write access to the usbfs node of the device (i.e. `/dev/bus/usb/001/002`).
+    /// The kernel rejects requests addressed to the HID interface itself while the HID driver is bound to it.
+    pub fn control_transfer<'a>(
+        &'a self, request_type: u8, request: u8, value: u16, index: u16, data: &'a mut [u8]
+    ) -> impl Future<Output = HidResult<usize>> + Send + 'a {
+        self.inner.control_transfer(request_type, request, value, index, data)
+    }
+
+    /// The serial number of this device, if it has one
+    ///
+    /// Unlike [SerialNumberExt::serial_number] this works consistently on every platform:
+    /// If the serial number was not retrieved during enumeration (e.g. because [EnumerateOptions::fetch_strings] was disabled),
+    /// it is read from the opened device. The result is cached afterward.
+    pub fn serial_number(&self) -> HidResult<Option<String>> {
+        if let Some(serial_number) = self.serial_number.get() {
+            return Ok(serial_number.clone());
+        }
+        let serial_number = match self.info.serial_number() {
+            Some(serial_number) if !serial_number.is_empty() => Some(serial_number.to_string()),
+            _ => self.inner.serial_number()?
+        };
+        Ok(self.serial_number.get_or_init(|| serial_number).clone())
+    }
+
+    /// The raw report descriptor of this device
+    ///
+    /// This allows parsing the report layout manually (see [ParsedDescriptor::parse]). It is currently only supported on Linux and macOS,
+    /// as Windows doesn't provide access to the original descriptor.
+    pub fn report_descriptor(&self) -> HidResult<Vec<u8>> {
+        self.inner.report_descriptor()
+    }
+
+    /// Lists the usages of all top-level collections of this device
+    ///
+    /// Enumeration returns a separate [DeviceInfo] for every collection, but all of them open the same device on Linux and macOS.
+    /// On Windows every collection is a separate device, so this only contains the usage of the opened device.
+    pub fn usages(&self) -> HidResult<Vec<Usage>> {
+        self.inner.usages()
+    }
+
+    /// Lists the ids of all feature reports supported by this device together with their length
+    ///
+    /// The lengths include the report id and are suitable for sizing the buffer passed to [Device::read_feature_report].
+    pub fn feature_report_ids(&self) -> HidResult<Vec<(u8, usize)>> {
+        self.inner.feature_report_ids()
+    }
+
+    /// Lists the ids of all output reports supported by this device together with their length
+    ///
+    /// The lengths include the report id. See [Report::validate_against] for checking a report before writing it.
+    pub fn output_report_ids(&self) -> HidResult<Vec<(u8, usize)>> {
+        self.inner.output_report_ids()
+    }
+
+    /// The number of input reports that were dropped because the queue was full when they arrived
+    ///
+    /// A steadily increasing count means that the reads can't keep up with the device (see [ReadConfig::queue_depth]).
+    /// Reports that are skipped because of [ReadConfig::latest_only] are counted as well, while [Device::clear_input_reports] is not.
+    /// On Linux only the reports skipped because of [ReadConfig::latest_only] can be detected, and the `win32` backend always returns `0`
+    /// as the OS doesn't report overflows of its queue.
+    pub fn dropped_reports(&self) -> u64 {
+        self.inner.dropped_reports()
+    }
+
+    /// Discards all input reports that have been received but not read yet
+    ///
+    /// This does not wait for new reports and is mainly useful for skipping stale data,
+    /// e.g. after switching the device into a different mode.
+    pub fn clear_input_reports(&self) -> HidResult<()> {
+        debug_assert!(self.mode.readable());
+        self.inner.clear_input_reports()
+    }
+
     /// Retrieves the [DeviceInfo] associated with this device
     pub fn info(&self) -> &DeviceInfo {
         &self.info
     }
 }
 
+fn report_stream<D: Borrow<Device> + Send>(device: D) -> impl Stream<Item = HidResult<Report>> + Send {
+    unfold(Some((device, Vec::new())), |state| async move {
+        let (device, mut buf) = state?;
+        if buf.is_empty() {
+            match device.borrow().inner.max_input_report_len() {
+                Ok(len) => buf.resize(len.max(1), 0),
+                Err(err) => return Some((Err(err), None))
+            }
+        }
+        match device.borrow().read_input_report_full(&mut buf).await {
+            Ok(outcome) => Some((Ok(outcome.to_report(&buf)), Some((device, buf)))),
+            // The error of a read from a removed device carries no useful information
+            Err(_) if !device.borrow().is_connected() => None,
+            Err(err) => Some((Err(err), None))
+        }
+    })
+}
+
 /// An opaque struct that wraps the OS specific identifier of a device
-#[derive(Hash, Clone, Eq, PartialEq)]
+///
+/// The ordering of ids is stable but otherwise meaningless. It is mainly useful for processing devices in a deterministic order.
+#[derive(Hash, Clone, Eq, PartialEq, Ord, PartialOrd)]
 #[repr(transparent)]
 pub struct DeviceId(BackendDeviceId);
 
@@ -125,12 +1032,59 @@ impl From<BackendDeviceId> for DeviceId {
     }
 }
 
+impl DeviceId {
+    /// Opens the device with this id in the requested [AccessMode] without having to search for its [DeviceInfo] manually
+    ///
+    /// See [DeviceInfo::from_id] for more information.
+    pub async fn open(&self, mode: AccessMode) -> HidResult<Device> {
+        DeviceInfo::from_id(self).await?.open(mode).await
+    }
+}
+
 impl Debug for DeviceId {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self.0)
     }
 }
 
+/// Formats the device identifier in the format accepted by [DeviceId::from_str]
+///
+/// This allows persisting the id of a device, for example to reopen the device selected by the user in a previous run.
+/// The device paths on Linux and Windows are reasonably stable as long as the device stays connected to the same port,
+/// but the registry entry ids on macOS are reassigned after every reboot and whenever the device is reconnected.
+impl Display for DeviceId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&backend::format_device_id(&self.0))
+    }
+}
+
+/// Parses the OS specific representation of a device identifier
+///
+/// The accepted formats are:
+/// - **Linux:** the device node (`/dev/hidraw0` or `hidraw0`) or the sysfs path (`/sys/class/hidraw/hidraw0`)
+/// - **Windows:** the device interface path (`\\?\HID#VID_046D&PID_C52B...`)
+/// - **macOS:** the registry entry id as decimal number
+///
+/// The identifier is only validated syntactically, so opening the device can still fail.
+impl FromStr for DeviceId {
+    type Err = HidError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        backend::parse_device_id(s).map(Self)
+    }
+}
+
+/// The result of [DeviceInfo::access_state]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AccessState {
+    /// The device can be opened
+    Available,
+    /// Another handle (of this process, another process or the OS itself) holds the device exclusively
+    BusyExclusive,
+    /// The current user lacks the permissions to open the device
+    PermissionDenied
+}
+
 /// An enum that controls how a device will be opened
 ///
 /// This mainly influences the flags passed to the underlying OS api,
@@ -154,5 +1108,145 @@ impl AccessMode {
     }
 }
 
+/// Settings that control how input reports are handled after opening a device
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct ReadConfig {
+    /// Only deliver input reports that arrive after the first call to [Device::read_input_report]
+    ///
+    /// By default, reports are collected as soon as the device is opened, so the first read might return outdated data.
+    pub lazy: bool,
+    /// Only deliver input reports that belong to the collection described by [DeviceInfo::usage_page] and [DeviceInfo::usage_id]
+    ///
+    /// Windows exposes every top-level collection as a separate device, so this setting has no effect there.
+    /// Linux and macOS deliver the reports of all collections of an interface together. On these platforms the reports are
+    /// filtered by their report id, which only works if the device uses numbered reports.
+    /// Setting [ReadConfig::report_id_mode] to [ReportIdMode::Unnumbered] disables the filter as well.
+    pub filter_collection: bool,
+    /// Only keep the most recent input report instead of queueing them
+    ///
+    /// This is useful for real-time applications that are only interested in the current state of the device.
+    /// Every report that hasn't been read by the time a newer one arrives is intentionally dropped.
+    ///
+    /// Windows always buffers at least two reports, so the returned report can be one report behind on this platform.
+    pub latest_only: bool,
+    /// The number of input reports that are buffered before the oldest ones get dropped, `64` by default
+    ///
+    /// Fast devices (i.e. mice with a polling rate of 1000Hz) might need a larger queue if the reads can't keep up.
+    /// Windows buffers at least 2 and at most 512 reports. On Linux, the queue is managed by the kernel and always holds 64 reports.
+    /// This setting is ignored if [ReadConfig::latest_only] is set.
+    pub queue_depth: Option<usize>,
+    /// What happens once the input report queue is full
+    ///
+    /// See [OverflowPolicy] for the available options.
+    pub overflow: OverflowPolicy,
+    /// Request an input report from the device if it didn't send one within the given duration
+    ///
+    /// Some devices only start sending input reports after the host asked for one (i.e. the `GET_REPORT` request).
+    /// If [Device::read_input_report] doesn't receive a report in time, it requests one with [Device::get_input_report]
+    /// and returns it like a regular input report. Devices with numbered reports are asked for the first report of the collection.
+    pub solicit_on_idle: Option<Duration>,
+    /// Override whether the input reports of the device start with a report id
+    ///
+    /// By default, this is determined from the report descriptor when the device is opened.
+    /// This is only necessary for devices whose reports don't match their descriptor.
+    pub report_id_mode: Option<ReportIdMode>,
+    /// Prevent other handles (including those of other processes) from opening the device while it is open
+    ///
+    /// Opening fails with the underlying sharing violation if the device is already in use.
+    /// This is supported by the `win32` backend and on macOS (where the device is seized from the OS);
+    /// the other backends refuse to open the device when this is set.
+    pub exclusive: bool
+}
+
+/// Decides what happens to new input reports when the queue is full
+///
+/// Input reports are pushed into the queue from a callback on an OS thread, which must not block.
+/// So there is no truly lossless mode: once the queue reached its limit, the oldest report is dropped to make room for the newest one
+/// (see [Device::dropped_reports]).
+///
+/// On Linux the queue is managed by the kernel and always holds 64 reports, so this setting has no effect there.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum OverflowPolicy {
+    /// Keep [ReadConfig::queue_depth] reports and drop the oldest one when a new report arrives
+    #[default]
+    DropOldest,
+    /// Let the queue grow beyond [ReadConfig::queue_depth] up to the given number of reports before dropping the oldest one
+    ///
+    /// Memory is only allocated for reports that actually pile up, so the limit can be much larger than the usual queue depth.
+    /// The trade-off is latency: a consumer that falls behind reads increasingly stale reports until it catches up.
+    /// With the `win32` backend the limit is capped at 512 reports.
+    Grow(usize)
+}
+
+/// Whether the reports of a device are prefixed with a report id
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ReportIdMode {
+    /// Every report starts with a non-zero report id
+    Numbered,
+    /// The reports only consist of the payload
+    Unnumbered
+}
+
+impl ReadConfig {
+    /// The number of input reports that are buffered before the oldest ones get dropped
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    pub(crate) fn queue_capacity(&self) -> usize {
+        match self.latest_only {
+            true => 1,
+            false => self.queue_depth.unwrap_or(64).max(1)
+        }
+    }
+
+    /// The number of input reports that can be queued before the oldest ones get dropped, taking [ReadConfig::overflow] into account
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    pub(crate) fn queue_limit(&self) -> usize {
+        match self.overflow {
+            OverflowPolicy::Grow(limit) if !self.latest_only => limit.max(self.queue_capacity()),
+            _ => self.queue_capacity()
+        }
+    }
+
+    /// Whether input reports start with a report id, unless overridden by [ReadConfig::report_id_mode] this is decided by `declared`
+    pub(crate) fn numbered_reports(&self, declared: impl FnOnce() -> bool) -> bool {
+        match self.report_id_mode {
+            Some(mode) => mode == ReportIdMode::Numbered,
+            None => declared()
+        }
+    }
+}
+
+/// Controls which information is queried by [DeviceInfo::enumerate_with_options]
+///
+/// On Windows, the device has to be opened to retrieve its strings and capabilities, which makes up most of the enumeration time.
+/// Fields that are skipped are left empty (`name` and [SerialNumberExt::serial_number]) or set to `0` (`usage_id` and `usage_page`).
+/// Skipping them doesn't prevent the device from being opened later.
+///
+/// The other backends get this information cheaply and ignore these options.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct EnumerateOptions {
+    /// Read the product and serial number strings of the device
+    pub fetch_strings: bool,
+    /// Read the usage page and usage id from the device capabilities
+    pub fetch_caps: bool,
+    /// Reuse the information of devices that were already present during the previous enumeration
+    ///
+    /// This avoids opening every device again when enumerating repeatedly (e.g. to refresh a device list).
+    /// Devices that are no longer present are removed from the cache during every enumeration that uses it.
+    /// See [DeviceInfo::clear_enumeration_cache] for discarding the cache manually.
+    ///
+    /// Only the Win32 backend has to open devices during enumeration, so this is ignored on all other platforms.
+    pub use_cache: bool
+}
+
+impl Default for EnumerateOptions {
+    fn default() -> Self {
+        Self {
+            fetch_strings: true,
+            fetch_caps: true,
+            use_cache: false
+        }
+    }
+}
+
 assert_impl_all!(Device: Send, Sync);
 assert_impl_all!(DeviceInfo: Send, Sync);
\ No newline at end of file
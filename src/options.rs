@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use crate::{AccessMode, OverflowPolicy, ReadConfig, ReportIdMode};
+
+/// A builder for all settings that can be chosen when opening a device
+///
+/// This combines the [AccessMode] and the [ReadConfig] into a single value for [DeviceInfo::open_with](crate::DeviceInfo::open_with).
+/// Every setting is set through a named method and all settings that aren't set keep their default value.
+/// If neither [OpenOptions::read] nor [OpenOptions::write] is requested, the device is opened for reading and writing.
+///
+/// ```
+/// # use async_hid::OpenOptions;
+/// let options = OpenOptions::new()
+///     .read()
+///     .write()
+///     .lazy(true)
+///     .queue_depth(256);
+/// ```
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    config: ReadConfig
+}
+
+impl OpenOptions {
+    /// Creates a new set of options with every setting at its default value
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open the device for reading
+    pub fn read(mut self) -> Self {
+        self.read = true;
+        self
+    }
+
+    /// Open the device for writing
+    pub fn write(mut self) -> Self {
+        self.write = true;
+        self
+    }
+
+    /// See [ReadConfig::lazy]
+    pub fn lazy(mut self, lazy: bool) -> Self {
+        self.config.lazy = lazy;
+        self
+    }
+
+    /// See [ReadConfig::filter_collection]
+    pub fn filter_collection(mut self, filter_collection: bool) -> Self {
+        self.config.filter_collection = filter_collection;
+        self
+    }
+
+    /// See [ReadConfig::latest_only]
+    pub fn latest_only(mut self, latest_only: bool) -> Self {
+        self.config.latest_only = latest_only;
+        self
+    }
+
+    /// See [ReadConfig::queue_depth]
+    pub fn queue_depth(mut self, queue_depth: usize) -> Self {
+        self.config.queue_depth = Some(queue_depth);
+        self
+    }
+
+    /// See [ReadConfig::overflow]
+    pub fn overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.config.overflow = overflow;
+        self
+    }
+
+    /// See [ReadConfig::solicit_on_idle]
+    pub fn solicit_on_idle(mut self, idle: Duration) -> Self {
+        self.config.solicit_on_idle = Some(idle);
+        self
+    }
+
+    /// See [ReadConfig::report_id_mode]
+    pub fn report_id_mode(mut self, mode: ReportIdMode) -> Self {
+        self.config.report_id_mode = Some(mode);
+        self
+    }
+
+    /// See [ReadConfig::exclusive]
+    ///
+    /// Unlike the other settings, this isn't silently ignored where it is unsupported: the `hidraw` and `winrt` backends
+    /// fail to open the device with an error instead of handing out a handle that other processes can still open.
+    pub fn exclusive(mut self, exclusive: bool) -> Self {
+        self.config.exclusive = exclusive;
+        self
+    }
+
+    /// The access mode resulting from the calls to [OpenOptions::read] and [OpenOptions::write]
+    pub fn access_mode(&self) -> AccessMode {
+        match (self.read, self.write) {
+            (true, false) => AccessMode::Read,
+            (false, true) => AccessMode::Write,
+            _ => AccessMode::ReadWrite
+        }
+    }
+
+    /// The input report settings collected by this builder
+    pub fn read_config(&self) -> ReadConfig {
+        self.config
+    }
+}
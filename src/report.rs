@@ -0,0 +1,116 @@
+use crate::{ensure, Device, HidError, HidResult};
+
+/// The different kinds of reports defined by the HID specification
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ReportType {
+    Input,
+    Output,
+    Feature
+}
+
+/// A HID report consisting of a report id and the associated payload
+///
+/// Devices that don't use numbered reports always use the report id `0`.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
+pub struct Report {
+    id: u8,
+    data: Vec<u8>
+}
+
+impl Report {
+    /// Creates a new report from a report id and the payload **without** the report id
+    pub fn new(id: u8, data: impl Into<Vec<u8>>) -> Self {
+        Self { id, data: data.into() }
+    }
+
+    /// Creates a new report from a buffer in the usual wire format where the first byte is the report id
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        match bytes.split_first() {
+            Some((id, data)) => Self::new(*id, data),
+            None => Self::default()
+        }
+    }
+
+    /// The report id of this report
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// The payload of this report **without** the report id
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consumes the report and returns the payload **without** the report id
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Returns the report in the usual wire format where the first byte is the report id
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.data.len() + 1);
+        bytes.push(self.id);
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    /// Checks whether this report is a valid output report for the given device
+    ///
+    /// This verifies that the report id is `0` if and only if the device doesn't use numbered reports,
+    /// that the device has an output report with this id and that the report is not longer than the device expects.
+    /// Writing an invalid report either fails at the OS level or gets silently truncated.
+    pub fn validate_against(&self, device: &Device) -> HidResult<()> {
+        let reports = device.output_report_ids()?;
+        ensure!(!reports.is_empty(), HidError::custom("Device has no output reports"));
+        let numbered = reports.iter().any(|(id, _)| *id != 0);
+        ensure!(
+            numbered == (self.id != 0),
+            HidError::custom(match numbered {
+                true => "Device uses numbered reports, but the report id is 0",
+                false => "Device doesn't use numbered reports, so the report id must be 0"
+            })
+        );
+        let len = reports
+            .iter()
+            .find_map(|(id, len)| (*id == self.id).then_some(*len))
+            .ok_or_else(|| HidError::custom(format!("Device has no output report with id {}", self.id)))?;
+        ensure!(
+            self.data.len() < len,
+            HidError::custom(format!("Report is {} bytes long, but the device expects at most {} bytes", self.data.len() + 1, len))
+        );
+        Ok(())
+    }
+}
+
+/// A HID usage consisting of a usage page and a usage id
+///
+/// The meaning of the different usages is defined in the *HID Usage Tables* document.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Usage {
+    pub page: u16,
+    pub id: u16
+}
+
+impl Usage {
+    pub const fn new(page: u16, id: u16) -> Self {
+        Self { page, id }
+    }
+}
+
+impl From<(u16, u16)> for Usage {
+    fn from((page, id): (u16, u16)) -> Self {
+        Self::new(page, id)
+    }
+}
+
+/// Interprets the lowest `bits` bits of `value` as two's complement number
+pub(crate) fn sign_extend(value: u64, bits: usize) -> i64 {
+    match bits {
+        1..=63 => {
+            let shift = 64 - bits;
+            ((value << shift) as i64) >> shift
+        }
+        _ => value as i64
+    }
+}
@@ -0,0 +1,60 @@
+//! Serde support for the types that are not covered by `#[derive]`
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::DeviceId;
+
+/// The serialized form of [DeviceId]
+///
+/// The platform is stored alongside the id, so ids from other platforms are rejected with a clear error
+/// instead of being misinterpreted. Every variant is available on every platform for this reason.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "platform", rename_all = "lowercase")]
+enum TaggedDeviceId {
+    Linux { id: String },
+    Windows { id: String },
+    Macos { id: u64 }
+}
+
+impl TaggedDeviceId {
+    fn platform(&self) -> &'static str {
+        match self {
+            TaggedDeviceId::Linux { .. } => "linux",
+            TaggedDeviceId::Windows { .. } => "windows",
+            TaggedDeviceId::Macos { .. } => "macos"
+        }
+    }
+}
+
+impl Serialize for DeviceId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[cfg(target_os = "linux")]
+        let tagged = TaggedDeviceId::Linux {
+            id: crate::backend::format_device_id(&self.0)
+        };
+        #[cfg(target_os = "windows")]
+        let tagged = TaggedDeviceId::Windows {
+            id: crate::backend::format_device_id(&self.0)
+        };
+        #[cfg(target_os = "macos")]
+        let tagged = TaggedDeviceId::Macos { id: self.0.into() };
+        tagged.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DeviceId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tagged = TaggedDeviceId::deserialize(deserializer)?;
+        match tagged {
+            #[cfg(target_os = "linux")]
+            TaggedDeviceId::Linux { id } => crate::backend::parse_device_id(&id).map(Self).map_err(D::Error::custom),
+            #[cfg(target_os = "windows")]
+            TaggedDeviceId::Windows { id } => crate::backend::parse_device_id(&id).map(Self).map_err(D::Error::custom),
+            #[cfg(target_os = "macos")]
+            TaggedDeviceId::Macos { id } => Ok(Self(id.into())),
+            #[allow(unreachable_patterns)]
+            other => Err(D::Error::custom(format!("Can't use a device id from {} on this platform", other.platform())))
+        }
+    }
+}
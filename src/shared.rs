@@ -0,0 +1,96 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use async_channel::{bounded, Receiver, Sender, TrySendError};
+use futures_lite::FutureExt;
+
+use crate::{Device, HidResult, Report};
+
+/// The number of reports that are buffered for every reader before new reports get dropped
+const SUBSCRIBER_CAPACITY: usize = 64;
+
+/// A read handle that can be cloned to let multiple consumers see every input report of a device
+///
+/// This struct can be obtained by calling [Device::into_shared_reader]. Every clone receives its own copy of every input report
+/// that arrives after the clone was created. There is no background task: whichever reader waits for a report reads it from the
+/// device and distributes it to all other readers. Readers that fall more than 64 reports behind miss the newer reports.
+pub struct SharedDeviceReader {
+    shared: Arc<Shared>,
+    receiver: Receiver<Report>
+}
+
+struct Shared {
+    device: Device,
+    buffer: async_lock::Mutex<Vec<u8>>,
+    subscribers: Mutex<Vec<Sender<Report>>>
+}
+
+impl SharedDeviceReader {
+    pub(crate) fn new(device: Device) -> Self {
+        let shared = Arc::new(Shared {
+            device,
+            buffer: async_lock::Mutex::new(Vec::new()),
+            subscribers: Mutex::new(Vec::new())
+        });
+        Self::subscribe(shared)
+    }
+
+    fn subscribe(shared: Arc<Shared>) -> Self {
+        let (sender, receiver) = bounded(SUBSCRIBER_CAPACITY);
+        shared
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(sender);
+        Self { shared, receiver }
+    }
+
+    /// The device this reader belongs to
+    ///
+    /// This can be used to write to the device, but reading from it directly takes the reports away from all readers.
+    pub fn device(&self) -> &Device {
+        &self.shared.device
+    }
+
+    /// Read the next input report that arrived after this reader was created
+    ///
+    /// Errors are only returned to the reader that happened to read from the device, the other readers keep waiting.
+    pub async fn read_report(&self) -> HidResult<Report> {
+        let receive = async {
+            // The sender is only removed once the receiver is dropped
+            Ok(self.receiver.recv().await.expect("Subscription got dropped unexpectedly"))
+        };
+        receive.or(self.read_from_device()).await
+    }
+
+    async fn read_from_device(&self) -> HidResult<Report> {
+        let mut buf = self.shared.buffer.lock().await;
+        // Another reader might have distributed a report while we were waiting for the lock
+        if let Ok(report) = self.receiver.try_recv() {
+            return Ok(report);
+        }
+        if buf.is_empty() {
+            buf.resize(self.shared.device.inner.max_input_report_len()?.max(1), 0);
+        }
+        let report = self.shared.device.read_input_report_full(&mut buf).await?.to_report(&buf);
+        self.shared
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|subscriber| match subscriber.try_send(report.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    log::trace!("[{}] Dropping input report because a shared reader is falling behind", self.shared.device.info.id);
+                    true
+                }
+                Err(TrySendError::Closed(_)) => false
+            });
+        // Our own queue was empty while holding the lock, so the report we just sent is the only one in it
+        Ok(self.receiver.try_recv().unwrap_or(report))
+    }
+}
+
+impl Clone for SharedDeviceReader {
+    fn clone(&self) -> Self {
+        Self::subscribe(self.shared.clone())
+    }
+}